@@ -1,23 +1,66 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Response, StdResult, WasmMsg,
+    entry_point, from_json, to_json_binary, Addr, BankMsg, Binary, Coin,
+    CosmosMsg, Deps, DepsMut, Env, IbcMsg, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, Storage, SubMsg, SubMsgResult, Timestamp,
+    Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::Bound;
+use cw_utils::parse_execute_response_data;
 use itertools::Itertools;
+use semver::Version;
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, MasterMsg, QueryMsg};
-use crate::state::{get_key, set_key, State, ACTIVE_RECOVERY, SLAVES, STATE};
+use crate::msg::{
+    BatchMode, BatchResult, ChainSlaveKind, ExecuteMsg,
+    GetSlaveCodeIdResponse, GuardianSignature, InstantiateMsg, MasterMsg,
+    MasterQueryMsg, MigrateMsg, QueryMsg, RecoveryApproval, SlaveExecuteMsg,
+    SlaveHostResponse,
+};
+use crate::state::{
+    GuardianPubkey, LimitExceededAction, PendingLimit, Proposal,
+    ProposalStatus, RecoveryMember, Reservation, SessionKey, SlaveInfo,
+    SlaveKind, State, Transaction, TxStatus, ACTIVE_RECOVERY, CONFIG_VERSION,
+    EXPECTED_ICAS, EXPECTED_SLAVES, LAST_NONCE, PENDING_ESCALATION,
+    PENDING_LIMITS, PROPOSALS, PROPOSAL_COUNT, RESERVATIONS,
+    RETRACTED_APPROVALS, SESSION_KEYS, SLAVES, SPENDING_LIMITS, STATE,
+    SYNCED_CONFIGS, TRANSACTIONS, TX_COUNT,
+};
+use crate::{history, ibc, ica, migrations, session, signed, spending};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:host";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-// #[repr(u8)]
-// enum ReplyKind {
-//     ReplySamechainTransaction = 1,
-// }
+// One week
+const DEFAULT_PROPOSAL_LIFETIME: u64 = 7 * 24 * 60 * 60;
+// Two days
+const DEFAULT_RECOVERY_DELAY: u64 = 2 * 24 * 60 * 60;
+// One week
+const DEFAULT_RECOVERY_LIFETIME: u64 = 7 * 24 * 60 * 60;
+// Thirty days, so that a stolen owner key cannot block recovery forever
+const MAX_RECOVERY_DELAY: u64 = 30 * 24 * 60 * 60;
+// Two days, matching the default recovery delay
+const LIMIT_LOOSENING_DELAY: u64 = 2 * 24 * 60 * 60;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+#[repr(u8)]
+enum ReplyKind {
+    ReplyExecuteBatch = 1,
+}
+
+impl std::convert::TryFrom<u64> for ReplyKind {
+    type Error = &'static str;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::ReplyExecuteBatch),
+            _ => Err("Unknown"),
+        }
+    }
+}
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -36,13 +79,44 @@ pub fn instantiate(
         recovery_approvals_needed: msg.recovery_approvals_needed,
         transfer_ownership_approvals_needed: msg
             .transfer_ownership_approvals_needed,
+        proposal_approvals_needed: msg
+            .proposal_approvals_needed
+            .unwrap_or((msg.approval_pool.len() as u32).max(1)),
+        limit_exceeded_action: LimitExceededAction::Reject,
+        recovery_delay: msg.recovery_delay.unwrap_or(DEFAULT_RECOVERY_DELAY),
+        recovery_lifetime: msg
+            .recovery_lifetime
+            .unwrap_or(DEFAULT_RECOVERY_LIFETIME),
+        recovery_initiator: None,
+        recovery_expires_at: None,
+        recovery_unlocks_at: None,
+        recovery_nonce: 0,
+        owner_pubkey: msg.owner_pubkey,
         chain: msg.chain,
     };
+    if let Some(pubkey) = &state.owner_pubkey {
+        signed::validate_pubkey(pubkey)?;
+    }
+    if state.recovery_pool.iter().any(|m| m.weight == 0) {
+        return Err(ContractError::InvalidWeight {});
+    }
+    for pubkey in state.recovery_pool.iter().filter_map(|m| m.pubkey.as_ref())
+    {
+        signed::validate_guardian_pubkey(pubkey)?;
+    }
+    if state.recovery_delay > MAX_RECOVERY_DELAY {
+        return Err(ContractError::RecoveryDelayTooLong {
+            max: MAX_RECOVERY_DELAY,
+        });
+    }
+    execute::validate_approvals_needed(&state)?;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
+    PROPOSAL_COUNT.save(deps.storage, &0)?;
+    TX_COUNT.save(deps.storage, &0)?;
 
     let recovery_pool_repr =
-        &msg.recovery_pool.iter().map(|x| x.to_string()).join("\",\"");
+        &msg.recovery_pool.iter().map(|x| x.addr.to_string()).join("\",\"");
     let approval_pool_repr =
         &msg.approval_pool.iter().map(|x| x.to_string()).join("\",\"");
 
@@ -59,6 +133,48 @@ pub fn instantiate(
             format!("[\"{}\"]", approval_pool_repr),
         ))
 }
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    deps: DepsMut,
+    env: Env,
+    _msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+    let parse = |version: &str| {
+        Version::parse(version)
+            .map_err(|e| ContractError::InvalidVersion(e.to_string()))
+    };
+    let previous = parse(&stored.version)?;
+    if previous > parse(CONTRACT_VERSION)? {
+        return Err(ContractError::CannotDowngrade {
+            previous_version: stored.version,
+        });
+    }
+
+    if previous < Version::new(0, 2, 0) {
+        migrations::from_v0_1(
+            deps.storage,
+            &env,
+            DEFAULT_RECOVERY_DELAY,
+            DEFAULT_RECOVERY_LIFETIME,
+        )?;
+    }
+    if previous < Version::new(0, 3, 0) {
+        migrations::from_v0_2(deps.storage, &deps.querier, &env)?;
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("previous_version", stored.version)
+        .add_attribute("version", CONTRACT_VERSION))
+}
+
 macro_rules! require_owner {
     ($info:ident, $state:ident) => {
         if $info.sender != $state.owner {
@@ -70,40 +186,154 @@ macro_rules! require_owner {
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::AddRecoveryMember { member } => {
-            execute::add_recovery_member(deps, info, member)
+        ExecuteMsg::AddRecoveryMember {
+            member,
+            weight,
+            required,
+            pubkey,
+            chain,
+        } => execute::add_recovery_member(
+            deps,
+            env,
+            info,
+            RecoveryMember { addr: member, weight, required, pubkey, chain },
+        ),
+        ExecuteMsg::UpdateGuardianPubkey { pubkey } => {
+            execute::update_guardian_pubkey(deps, info, pubkey)
+        }
+        ExecuteMsg::SubmitRecoveryApprovals { payload, signatures } => {
+            execute::submit_recovery_approvals(deps, env, payload, signatures)
         }
         ExecuteMsg::AddApprovalMember { member } => {
             execute::add_approval_member(deps, info, member)
         }
         ExecuteMsg::RemoveRecoveryMember { member } => {
-            execute::remove_recovery_member(deps, info, member)
+            execute::remove_recovery_member(deps, env, info, member)
         }
         ExecuteMsg::RemoveApprovalMember { member } => {
             execute::remove_approval_member(deps, info, member)
         }
         ExecuteMsg::RegisterSlave { chain, addr } => {
-            execute::register_slave(deps, info, chain, addr)
+            execute::register_slave(deps, env, info, chain, addr)
+        }
+        ExecuteMsg::UnregisterSlave { chain } => {
+            execute::unregister_slave(deps, info, chain)
+        }
+        ExecuteMsg::AllowRemoteSlave { chain, port_id } => {
+            execute::allow_remote_slave(deps, info, chain, port_id)
+        }
+        ExecuteMsg::RegisterInterchainAccount { chain } => {
+            execute::register_interchain_account(deps, env, info, chain)
+        }
+        ExecuteMsg::ConfirmInterchainAccount { chain } => {
+            execute::confirm_interchain_account(deps, env, chain)
+        }
+        ExecuteMsg::ReplaceSlave { chain, addr } => {
+            execute::replace_slave(deps, env, info, chain, addr)
+        }
+        ExecuteMsg::ExecuteBatch { msgs, mode } => {
+            execute::execute_batch(deps, env, info, msgs, mode)
+        }
+        ExecuteMsg::SyncSlave { chain } => {
+            execute::sync_slave(deps, env, chain)
+        }
+        ExecuteMsg::ExecuteOnChain { chain, msgs } => {
+            execute::execute_on_chain(deps, env, info, chain, msgs)
         }
         ExecuteMsg::ExecuteSameChain { body_proxy } => {
-            execute::execute_samechain_transaction(deps, info, body_proxy)
+            execute::execute_samechain_transaction(
+                deps, env, info, body_proxy,
+            )
         }
         ExecuteMsg::BeginSocialRecovery { target_addr } => {
-            execute::begin_social_recovery(deps, info, target_addr)
+            execute::begin_social_recovery(deps, env, info, target_addr)
         }
         ExecuteMsg::ApproveSocialRecovery { target_addr } => {
-            execute::approve_social_recovery(deps, info, target_addr)
+            execute::approve_social_recovery(deps, env, info, target_addr)
+        }
+        ExecuteMsg::VetoRecovery {} => execute::veto_recovery(deps, info),
+        ExecuteMsg::CancelRecovery {} => execute::cancel_recovery(deps, info),
+        ExecuteMsg::RetractRecoveryApproval {} => {
+            execute::retract_recovery_approval(deps, info)
+        }
+        ExecuteMsg::FinalizeRecovery {} => {
+            execute::finalize_recovery(deps, env)
+        }
+        ExecuteMsg::UpdateRecoveryDelay { delay } => {
+            execute::update_recovery_delay(deps, info, delay)
+        }
+        ExecuteMsg::UpdateRecoveryLifetime { lifetime } => {
+            execute::update_recovery_lifetime(deps, info, lifetime)
         }
         ExecuteMsg::BeginTransferOwnership { target_addr } => {
-            execute::begin_transfer_ownership(deps, info, target_addr)
+            execute::begin_transfer_ownership(deps, env, info, target_addr)
         }
         ExecuteMsg::ApproveTransferOwnership { target_addr } => {
-            execute::approve_transfer_ownership(deps, info, target_addr)
+            execute::approve_transfer_ownership(deps, env, info, target_addr)
+        }
+        ExecuteMsg::ProposeTransaction { msgs, expires_in } => {
+            execute::propose_transaction(deps, env, info, msgs, expires_in)
+        }
+        ExecuteMsg::ApproveProposal { proposal_id } => {
+            execute::approve_proposal(deps, env, info, proposal_id)
+        }
+        ExecuteMsg::CancelProposal { proposal_id } => {
+            execute::cancel_proposal(deps, info, proposal_id)
+        }
+        ExecuteMsg::UpdateProposalApprovalsNeeded { approvals_needed } => {
+            execute::update_proposal_approvals_needed(
+                deps,
+                info,
+                approvals_needed,
+            )
+        }
+        ExecuteMsg::SetSpendingLimit { denom, period, amount } => {
+            execute::set_spending_limit(
+                deps, env, info, denom, period, amount,
+            )
+        }
+        ExecuteMsg::RemoveSpendingLimit { denom, period } => {
+            execute::remove_spending_limit(deps, env, info, denom, period)
+        }
+        ExecuteMsg::UpdateLimitExceededAction { action } => {
+            execute::update_limit_exceeded_action(deps, env, info, action)
+        }
+        ExecuteMsg::ApplyLimitChanges {} => {
+            execute::apply_limit_changes(deps, env)
+        }
+        ExecuteMsg::AddSessionKey {
+            key,
+            expires_in,
+            spend_limit,
+            allowed_msgs,
+            allowed_contracts,
+        } => execute::add_session_key(
+            deps,
+            env,
+            info,
+            key,
+            expires_in,
+            SessionKey {
+                expires_at: Timestamp::default(),
+                spend_limit,
+                spent: vec![],
+                allowed_msgs,
+                allowed_contracts,
+            },
+        ),
+        ExecuteMsg::RevokeSessionKey { key } => {
+            execute::revoke_session_key(deps, info, key)
+        }
+        ExecuteMsg::ExecuteSigned { payload, signature } => {
+            execute::execute_signed(deps, env, info, payload, signature)
+        }
+        ExecuteMsg::UpdateOwnerPubkey { pubkey } => {
+            execute::update_owner_pubkey(deps, info, pubkey)
         }
     }
 }
@@ -124,18 +354,49 @@ mod execute {
 
     pub fn add_recovery_member(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
-        member: Addr,
+        member: RecoveryMember,
     ) -> Result<Response, ContractError> {
-        STATE.update(deps.storage, |mut state| {
+        if member.weight == 0 {
+            return Err(ContractError::InvalidWeight {});
+        }
+        if let Some(pubkey) = &member.pubkey {
+            signed::validate_guardian_pubkey(pubkey)?;
+        }
+        let state = STATE.update(deps.storage, |mut state| {
             require_owner!(info, state);
-            if state.recovery_pool.contains(&member) {
+            if state.recovery_member(&member.voter()).is_some() {
                 return Err(ContractError::MemberAlreadyAdded {});
             }
             state.recovery_pool.push(member);
             Ok(state)
         })?;
-        Ok(Response::new().add_attribute("action", "add_recovery_member"))
+        // Remote slaves check their guardians before relaying approvals
+        Ok(Response::new()
+            .add_attribute("action", "add_recovery_member")
+            .add_messages(ibc::sync_slaves(deps.storage, &env)?)
+            .add_message(update_guardians(&state)?))
+    }
+
+    pub fn update_guardian_pubkey(
+        deps: DepsMut,
+        info: MessageInfo,
+        pubkey: Option<GuardianPubkey>,
+    ) -> Result<Response, ContractError> {
+        if let Some(pubkey) = &pubkey {
+            signed::validate_guardian_pubkey(pubkey)?;
+        }
+        STATE.update(deps.storage, |mut state| {
+            let member = state
+                .recovery_pool
+                .iter_mut()
+                .find(|m| m.voter() == info.sender)
+                .ok_or(ContractError::MemberNotFound {})?;
+            member.pubkey = pubkey;
+            Ok::<_, ContractError>(state)
+        })?;
+        Ok(Response::new().add_attribute("action", "update_guardian_pubkey"))
     }
     pub fn add_approval_member(
         deps: DepsMut,
@@ -148,6 +409,7 @@ mod execute {
                 return Err(ContractError::MemberAlreadyAdded {});
             }
             state.approval_pool.push(member);
+            validate_approvals_needed(&state)?;
             Ok(state)
         })?;
         Ok(Response::new().add_attribute("action", "add_approval_member"))
@@ -155,18 +417,37 @@ mod execute {
 
     pub fn remove_recovery_member(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         member: Addr,
     ) -> Result<Response, ContractError> {
-        STATE.update(deps.storage, |mut state| {
+        let state = STATE.update(deps.storage, |mut state| {
             require_owner!(info, state);
-            if !state.recovery_pool.contains(&member) {
+            if state.recovery_member(&member).is_none() {
                 return Err(ContractError::MemberNotFound {});
             }
-            state.recovery_pool.retain(|x| x != member);
+            state.recovery_pool.retain(|x| x.voter() != member);
             Ok(state)
         })?;
-        Ok(Response::new().add_attribute("action", "remove_recovery_member"))
+        Ok(Response::new()
+            .add_attribute("action", "remove_recovery_member")
+            .add_messages(ibc::sync_slaves(deps.storage, &env)?)
+            .add_message(update_guardians(&state)?))
+    }
+
+    /// Keeps the master's guardian lookup up to date.
+    fn update_guardians(state: &State) -> StdResult<WasmMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: state.master.to_string(),
+            msg: to_json_binary(&MasterMsg::UpdateGuardians {
+                guardians: state
+                    .recovery_pool
+                    .iter()
+                    .map(RecoveryMember::voter)
+                    .collect(),
+            })?,
+            funds: vec![],
+        })
     }
     pub fn remove_approval_member(
         deps: DepsMut,
@@ -179,6 +460,8 @@ mod execute {
                 return Err(ContractError::MemberNotFound {});
             }
             state.approval_pool.retain(|x| x != member);
+            // Lower the threshold first, open proposals could never pass
+            validate_approvals_needed(&state)?;
             Ok(state)
         })?;
         Ok(Response::new().add_attribute("action", "remove_approval_member"))
@@ -186,6 +469,7 @@ mod execute {
 
     pub fn register_slave(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         chain: String,
         addr: Addr,
@@ -193,47 +477,425 @@ mod execute {
         if info.sender != addr {
             return Err(ContractError::Unauthorized {});
         }
-        set_key(
-            &SLAVES,
-            deps.storage,
-            chain.clone(),
-            deps.api.addr_validate(&addr.to_string())?,
-            true,
-        )?;
+        if SLAVES.has(deps.storage, &chain) {
+            return Err(ContractError::SlaveAlreadyRegistered {});
+        }
+        let addr = deps.api.addr_validate(addr.as_ref())?;
+        let state = STATE.load(deps.storage)?;
+        verify_slave_code(&deps, &env, &state, &chain, &addr)?;
+        let slave = SlaveInfo::new(&deps.querier, addr, env.block.time);
+        SLAVES.save(deps.storage, &chain, &slave)?;
         Ok(Response::new()
             .add_attribute("action", "register_slave")
-            .add_attribute("chain", chain.clone()))
+            .add_attribute("chain", chain))
+    }
+
+    /// Only accept instances of the slave code master uses for `chain`,
+    /// created by master for this host.
+    fn verify_slave_code(
+        deps: &DepsMut,
+        env: &Env,
+        state: &State,
+        chain: &str,
+        addr: &Addr,
+    ) -> Result<(), ContractError> {
+        let contract = deps.querier.query_wasm_contract_info(addr)?;
+        if contract.creator != state.master {
+            return Err(ContractError::Unauthorized {});
+        }
+        // Slaves of other wallets share the code, but not their host
+        let res: SlaveHostResponse = deps.querier.query_wasm_smart(
+            &state.master,
+            &MasterQueryMsg::GetSlaveHost { slave: addr.clone() },
+        )?;
+        if res.slave.map(|slave| slave.host)
+            != Some(env.contract.address.clone())
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        let res: GetSlaveCodeIdResponse = deps.querier.query_wasm_smart(
+            &state.master,
+            &MasterQueryMsg::GetSlaveCodeId { chain: chain.to_string() },
+        )?;
+        match res.code_id {
+            None => Err(ContractError::ChainNotSupported {}),
+            Some(code_id) if code_id != contract.code_id => {
+                Err(ContractError::Unauthorized {})
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    pub fn unregister_slave(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain: String,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        let slave = SLAVES
+            .may_load(deps.storage, &chain)?
+            .ok_or(ContractError::ChainNotRegistered {})?;
+        SLAVES.remove(deps.storage, &chain);
+        SYNCED_CONFIGS.remove(deps.storage, &chain);
+        let response = Response::new()
+            .add_attribute("action", "unregister_slave")
+            .add_attribute("chain", chain);
+        match slave.kind.channel_id() {
+            None => Ok(response),
+            Some(channel_id) => {
+                Ok(response.add_message(IbcMsg::CloseChannel {
+                    channel_id: channel_id.to_string(),
+                }))
+            }
+        }
+    }
+
+    /// Push the current config to the remote slave of `chain`, e.g. after
+    /// it reconnected or a sync kept failing.
+    pub fn sync_slave(
+        deps: DepsMut,
+        env: Env,
+        chain: String,
+    ) -> Result<Response, ContractError> {
+        let slave = SLAVES
+            .may_load(deps.storage, &chain)?
+            .ok_or(ContractError::ChainNotRegistered {})?;
+        let SlaveKind::Ibc { channel_id } = slave.kind else {
+            return Err(ContractError::NotRemoteSlave {});
+        };
+        let msg = ibc::sync_config(deps.storage, &env, &chain, channel_id)?;
+        Ok(Response::new()
+            .add_attribute("action", "sync_slave")
+            .add_attribute("chain", chain)
+            .add_message(msg))
+    }
+
+    /// Let the slave contract bound to `port_id` on a remote `chain`
+    /// connect, it is registered once the channel is open.
+    pub fn allow_remote_slave(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain: String,
+        port_id: String,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        if chain == state.chain {
+            return Err(ContractError::ChainNotSupported {});
+        }
+        if SLAVES.has(deps.storage, &chain) {
+            return Err(ContractError::SlaveAlreadyRegistered {});
+        }
+        // The connection is checked once the channel opens
+        ibc::remote_chain(
+            deps.as_ref(),
+            &state.master,
+            &chain,
+            ChainSlaveKind::WasmIbc,
+        )?;
+        EXPECTED_SLAVES.save(deps.storage, &port_id, &chain)?;
+        Ok(Response::new()
+            .add_attribute("action", "allow_remote_slave")
+            .add_attribute("chain", chain)
+            .add_attribute("port_id", port_id))
+    }
+
+    /// Open an interchain account on `chain` through the controller module,
+    /// over the connection the master registered for it. Also reopens the
+    /// account after a timeout closed its channel.
+    pub fn register_interchain_account(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        chain: String,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        let slave = SLAVES.may_load(deps.storage, &chain)?;
+        if slave.is_some_and(|s| !matches!(s.kind, SlaveKind::Ica { .. })) {
+            return Err(ContractError::SlaveAlreadyRegistered {});
+        }
+        let remote = ibc::remote_chain(
+            deps.as_ref(),
+            &state.master,
+            &chain,
+            ChainSlaveKind::Ica,
+        )?;
+        let connection_id = remote.connection_id.unwrap_or_default();
+        EXPECTED_ICAS.save(deps.storage, &chain, &connection_id)?;
+        Ok(Response::new()
+            .add_attribute("action", "register_interchain_account")
+            .add_attribute("chain", chain)
+            .add_attribute("connection_id", &connection_id)
+            .add_message(ica::register(
+                &env.contract.address,
+                &connection_id,
+            )))
+    }
+
+    /// Register the interchain account opened for `chain` as its slave,
+    /// once relayers completed the channel handshake.
+    pub fn confirm_interchain_account(
+        deps: DepsMut,
+        env: Env,
+        chain: String,
+    ) -> Result<Response, ContractError> {
+        let connection_id = EXPECTED_ICAS
+            .may_load(deps.storage, &chain)?
+            .ok_or(ContractError::UnexpectedSlave {})?;
+        let address = ica::account_address(
+            &deps.querier,
+            &env.contract.address,
+            &connection_id,
+        )?;
+        EXPECTED_ICAS.remove(deps.storage, &chain);
+        let slave = SlaveInfo {
+            addr: Addr::unchecked(address),
+            kind: SlaveKind::Ica { connection_id },
+            code_version: None,
+            registered_at: env.block.time,
+        };
+        SLAVES.save(deps.storage, &chain, &slave)?;
+        Ok(Response::new()
+            .add_attribute("action", "confirm_interchain_account")
+            .add_attribute("chain", chain)
+            .add_attribute("addr", slave.addr))
+    }
+
+    pub fn replace_slave(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        chain: String,
+        addr: Addr,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        let previous = SLAVES.may_load(deps.storage, &chain)?;
+        if previous.as_ref().is_some_and(|p| p.kind != SlaveKind::Local) {
+            return Err(ContractError::NotLocalSlave {});
+        }
+        let addr = deps.api.addr_validate(addr.as_ref())?;
+        verify_slave_code(&deps, &env, &state, &chain, &addr)?;
+        let slave = SlaveInfo::new(&deps.querier, addr, env.block.time);
+        SLAVES.save(deps.storage, &chain, &slave)?;
+        SYNCED_CONFIGS.remove(deps.storage, &chain);
+        let mut response = Response::new()
+            .add_attribute("action", "replace_slave")
+            .add_attribute("chain", chain)
+            .add_attribute("addr", slave.addr);
+        if let Some(previous) = previous {
+            response = response.add_attribute("previous", previous.addr);
+        }
+        Ok(response)
     }
 
     pub fn execute_samechain_transaction(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         proxy_msg: CosmosMsg,
+    ) -> Result<Response, ContractError> {
+        execute_on_slave(
+            deps,
+            env,
+            info,
+            None,
+            vec![proxy_msg],
+            BatchMode::Atomic,
+            "execute_samechain_transaction",
+        )
+    }
+
+    pub fn execute_batch(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msgs: Vec<CosmosMsg>,
+        mode: BatchMode,
+    ) -> Result<Response, ContractError> {
+        if msgs.is_empty() {
+            return Err(ContractError::EmptyBatch {});
+        }
+        execute_on_slave(deps, env, info, None, msgs, mode, "execute_batch")
+    }
+
+    pub fn execute_on_chain(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        chain: String,
+        msgs: Vec<CosmosMsg>,
+    ) -> Result<Response, ContractError> {
+        if msgs.is_empty() {
+            return Err(ContractError::EmptyBatch {});
+        }
+        execute_on_slave(
+            deps,
+            env,
+            info,
+            Some(chain),
+            msgs,
+            BatchMode::Atomic,
+            "execute_on_chain",
+        )
+    }
+
+    /// Run `msgs` on the slave for `chain`, host chain if not given.
+    fn execute_on_slave(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        chain: Option<String>,
+        msgs: Vec<CosmosMsg>,
+        mode: BatchMode,
+        method: &str,
     ) -> Result<Response, ContractError> {
         let state = STATE.load(deps.storage)?;
-        require_owner!(info, state);
-        if let Ok(slave_contract) =
-            get_key(&SLAVES, deps.storage, &state.chain.to_string())
-        {
-            let action = WasmMsg::Execute {
-                contract_addr: slave_contract.to_string(),
-                msg: to_binary(&proxy_msg)?,
-                funds: info.funds,
-            };
-            // let sub_msg = SubMsg::reply_on_success(
-            //     action,
-            //     ReplyKind::ReplySamechainTransaction as u64,
-            // );
-            Ok(Response::new()
-                .add_attribute("contract", "host")
-                .add_attribute("method", "execute_samechain_transaction")
-                // .add_submessage(sub_msg)
-                .add_message(action))
-        } else {
-            Err(ContractError::ChainNotRegistered {})
+        let chain = chain.unwrap_or_else(|| state.chain.clone());
+        let is_owner = info.sender == state.owner;
+        let slave = SLAVES
+            .may_load(deps.storage, &chain)?
+            .ok_or(ContractError::ChainNotRegistered {})?;
+        if !is_owner {
+            for msg in msgs.iter() {
+                session::use_key(
+                    deps.storage,
+                    env.block.time,
+                    &info.sender,
+                    &slave.addr,
+                    msg,
+                )?;
+            }
+        }
+        let is_local = slave.kind == SlaveKind::Local;
+        if !is_local && !info.funds.is_empty() {
+            return Err(ContractError::FundsNotSupported {});
+        }
+        let mut response = Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", method)
+            .add_attribute("chain", chain);
+        if !is_owner {
+            response = response.add_attribute("session_key", &info.sender);
+        }
+
+        match spending::spend(deps.storage, env.block.time, &msgs) {
+            Ok(()) => {}
+            Err(ContractError::Std(err)) => return Err(err.into()),
+            // Only the owner may escalate, session keys are rejected.
+            // Proposals always run on the host chain.
+            Err(err)
+                if is_owner
+                    && is_local
+                    && state.limit_exceeded_action
+                        == LimitExceededAction::Escalate =>
+            {
+                let id = create_proposal(
+                    deps,
+                    &env,
+                    &state,
+                    info.sender,
+                    msgs,
+                    None,
+                )?;
+                let response = response
+                    .add_attribute("escalated", err.to_string())
+                    .add_attribute("proposal_id", id.to_string());
+                if info.funds.is_empty() {
+                    return Ok(response);
+                }
+                // Do not leave attached funds on the host
+                return Ok(response.add_message(BankMsg::Send {
+                    to_address: slave.addr.to_string(),
+                    amount: info.funds,
+                }));
+            }
+            Err(err) => return Err(err),
+        }
+
+        match slave.kind {
+            SlaveKind::Local => {
+                let reservation = Reservation {
+                    session_key: (!is_owner).then(|| info.sender.clone()),
+                    funds: info.funds.clone(),
+                };
+                let (tx_id, sub_msg) = dispatch(
+                    deps.storage,
+                    &env,
+                    &info.sender,
+                    &slave.addr,
+                    msgs,
+                    mode,
+                    info.funds,
+                )?;
+                if reservation.session_key.is_some()
+                    || !reservation.funds.is_empty()
+                {
+                    RESERVATIONS.save(deps.storage, tx_id, &reservation)?;
+                }
+                Ok(response
+                    .add_attribute("tx_id", tx_id.to_string())
+                    .add_submessage(sub_msg))
+            }
+            SlaveKind::Ibc { channel_id } => {
+                let (tx_id, packet) = ibc::send_batch(
+                    deps.storage,
+                    &env,
+                    &info.sender,
+                    channel_id,
+                    msgs,
+                    mode,
+                )?;
+                Ok(response
+                    .add_attribute("tx_id", tx_id.to_string())
+                    .add_message(packet))
+            }
+            // Interchain account transactions are always atomic
+            SlaveKind::Ica { connection_id } => {
+                let (tx_id, msg) = ica::send_tx(
+                    deps.storage,
+                    &env,
+                    &info.sender,
+                    &connection_id,
+                    slave.addr.as_str(),
+                    msgs,
+                )?;
+                Ok(response
+                    .add_attribute("tx_id", tx_id.to_string())
+                    .add_message(msg))
+            }
         }
     }
 
+    /// Send `msgs` to the slave and record them in the history. The reply
+    /// completes the record and forwards the batch outcome as our data, or
+    /// gives back what the host charged if the slave failed.
+    fn dispatch(
+        storage: &mut dyn Storage,
+        env: &Env,
+        sender: &Addr,
+        slave_contract: &Addr,
+        msgs: Vec<CosmosMsg>,
+        mode: BatchMode,
+        funds: Vec<Coin>,
+    ) -> StdResult<(u64, SubMsg)> {
+        let tx_id = history::begin(storage, env, sender, &msgs)?;
+        let action = WasmMsg::Execute {
+            contract_addr: slave_contract.to_string(),
+            msg: to_json_binary(&SlaveExecuteMsg::ExecuteBatch {
+                msgs,
+                mode,
+            })?,
+            funds,
+        };
+        Ok((
+            tx_id,
+            SubMsg::reply_always(action, ReplyKind::ReplyExecuteBatch as u64),
+        ))
+    }
+
     fn can_transfer_ownership(
         deps: &DepsMut,
         why: &str,
@@ -241,68 +903,137 @@ mod execute {
         let state = STATE.load(deps.storage)?;
         let acs_needed = match why {
             "recovery" => state.recovery_approvals_needed,
-            // exclude self
-            "transfer_ownership" => {
-                state.transfer_ownership_approvals_needed + 1
-            }
+            // owner vote is not counted
+            "transfer_ownership" => state.transfer_ownership_approvals_needed,
             _ => panic!("Unknown ownership transfer reason."),
         };
-        let acs_got = ACTIVE_RECOVERY.len(deps.storage)?;
-        Ok(acs_got >= acs_needed)
+        let voters = ACTIVE_RECOVERY
+            .iter(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(state.approvals_weight(&voters) >= acs_needed
+            && state.required_approved(&voters))
+    }
+
+    fn clear_recovery(
+        storage: &mut dyn Storage,
+        state: &mut State,
+    ) -> StdResult<()> {
+        if state.potential_owner.is_some() {
+            // Invalidates off-chain approvals of the finished process
+            state.recovery_nonce += 1;
+        }
+        state.potential_owner = None;
+        state.recovery_method = None;
+        state.recovery_initiator = None;
+        state.recovery_expires_at = None;
+        state.recovery_unlocks_at = None;
+        STATE.save(storage, state)?;
+        while let Ok(Some(_)) = ACTIVE_RECOVERY.pop_back(storage) {}
+        Ok(())
+    }
+
+    /// Process that did not collect enough approvals before its deadline.
+    /// Timelocked recoveries never expire, anyone can finalize them.
+    fn is_expired(state: &State, env: &Env) -> bool {
+        state.recovery_unlocks_at.is_none()
+            && state
+                .recovery_expires_at
+                .is_some_and(|expires_at| env.block.time >= expires_at)
     }
 
     fn do_transfer_ownership(
         deps: DepsMut,
+        env: &Env,
     ) -> Result<Response, ContractError> {
-        let state = STATE.load(deps.storage)?;
+        let mut state = STATE.load(deps.storage)?;
         let old_owner = state.owner.clone();
-        if let Some(new_owner) = state.potential_owner {
-            STATE.update(
-                deps.storage,
-                |mut state| -> Result<_, ContractError> {
-                    state.owner = new_owner.clone();
-                    state.potential_owner = None;
-                    state.recovery_method = None;
-                    Ok(state)
-                },
-            )?;
-            while let Ok(Some(_)) = ACTIVE_RECOVERY.pop_back(deps.storage) {}
+        let recovery = state.recovery_method.as_deref() == Some("recovery");
+        if let Some(new_owner) = state.potential_owner.clone() {
+            state.owner = new_owner.clone();
+            state.owner_pubkey = None;
+            clear_recovery(deps.storage, &mut state)?;
+            // Keys and loosened limits of the previous owner must not
+            // outlive them
+            SESSION_KEYS.clear(deps.storage);
+            PENDING_LIMITS.clear(deps.storage);
+            PENDING_ESCALATION.remove(deps.storage);
             Ok(Response::new()
                 .add_attribute("contract", "host")
                 .add_attribute("method", "do_transfer_ownership")
                 .add_message(WasmMsg::Execute {
                     contract_addr: state.master.to_string(),
-                    msg: to_binary(&MasterMsg::UpdateOwner {
+                    msg: to_json_binary(&MasterMsg::UpdateOwner {
                         new_owner,
                         old_owner,
+                        recovery,
                     })?,
                     funds: vec![],
-                }))
+                })
+                .add_messages(ibc::sync_slaves(deps.storage, env)?))
         } else {
             panic!("Impossible situation: no new owner during recovery");
         }
     }
 
+    /// Called whenever enough approvals are collected. Ownership transfer
+    /// started by the owner completes immediately, social recovery is
+    /// locked for `recovery_delay` to let the owner veto it.
+    fn on_quorum(
+        deps: DepsMut,
+        env: &Env,
+        method: &str,
+    ) -> Result<Response, ContractError> {
+        let mut state = STATE.load(deps.storage)?;
+        if method != "recovery" || state.recovery_delay == 0 {
+            return do_transfer_ownership(deps, env);
+        }
+        let unlocks_at = match state.recovery_unlocks_at {
+            Some(unlocks_at) => unlocks_at,
+            None => {
+                let unlocks_at =
+                    env.block.time.plus_seconds(state.recovery_delay);
+                state.recovery_unlocks_at = Some(unlocks_at);
+                STATE.save(deps.storage, &state)?;
+                unlocks_at
+            }
+        };
+        Ok(Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "lock_recovery")
+            .add_attribute("unlocks_at", unlocks_at.to_string()))
+    }
+
+    fn start_recovery(
+        storage: &mut dyn Storage,
+        env: &Env,
+        initiator: Addr,
+        target_addr: Addr,
+        method: &str,
+    ) -> StdResult<()> {
+        let mut state = STATE.load(storage)?;
+        // Also drops leftovers of an expired process
+        clear_recovery(storage, &mut state)?;
+        ACTIVE_RECOVERY.push_back(storage, &initiator)?;
+        state.potential_owner = Some(target_addr);
+        state.recovery_method = Some(method.to_string());
+        state.recovery_initiator = Some(initiator);
+        state.recovery_expires_at =
+            Some(env.block.time.plus_seconds(state.recovery_lifetime));
+        STATE.save(storage, &state)
+    }
+
     fn _begin_recovery(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         target_addr: Addr,
         method: &str,
     ) -> Result<Response, ContractError> {
-        while let Ok(Some(_)) = ACTIVE_RECOVERY.pop_back(deps.storage) {}
-        ACTIVE_RECOVERY.push_back(deps.storage, &info.sender)?;
-        STATE.update(
-            deps.storage,
-            |mut state| -> Result<_, ContractError> {
-                state.potential_owner = Some(target_addr);
-                state.recovery_method = Some(method.to_string());
-                Ok(state)
-            },
-        )?;
+        start_recovery(deps.storage, &env, info.sender, target_addr, method)?;
 
         if can_transfer_ownership(&deps, method)? {
             // Maybe some idiot allows one approval
-            return do_transfer_ownership(deps);
+            return on_quorum(deps, &env, method);
         }
 
         Ok(Response::new()
@@ -312,6 +1043,7 @@ mod execute {
 
     pub fn begin_social_recovery(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         target_addr: Addr,
     ) -> Result<Response, ContractError> {
@@ -319,21 +1051,22 @@ mod execute {
         if target_addr == state.owner {
             return Err(ContractError::SelfRecovery {});
         }
-        if !state.recovery_pool.contains(&info.sender)
+        if state.recovery_member(&info.sender).is_none()
             || state.owner == info.sender
         {
             // Owner cannot participate in social recovery, use TransferOwnership instead
             // TODO: add separate err for that.
             return Err(ContractError::Unauthorized {});
         }
-        if let Some(_) = state.potential_owner {
+        if state.potential_owner.is_some() && !is_expired(&state, &env) {
             return Err(ContractError::AlreadyRecovering {});
         }
-        _begin_recovery(deps, info, target_addr, "recovery")
+        _begin_recovery(deps, env, info, target_addr, "recovery")
     }
 
     pub fn begin_transfer_ownership(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         target_addr: Addr,
     ) -> Result<Response, ContractError> {
@@ -345,21 +1078,22 @@ mod execute {
             // Only owner can initiate ownership transfer.
             return Err(ContractError::Unauthorized {});
         }
-        if let Some(_) = state.potential_owner {
+        if state.potential_owner.is_some() && !is_expired(&state, &env) {
             return Err(ContractError::AlreadyRecovering {});
         }
-        _begin_recovery(deps, info, target_addr, "transfer_ownership")
+        _begin_recovery(deps, env, info, target_addr, "transfer_ownership")
     }
 
     fn _approve_recovery(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         method: &str,
     ) -> Result<Response, ContractError> {
         ACTIVE_RECOVERY.push_back(deps.storage, &info.sender)?;
 
         if can_transfer_ownership(&deps, method)? {
-            return do_transfer_ownership(deps);
+            return on_quorum(deps, &env, method);
         }
 
         Ok(Response::new()
@@ -369,11 +1103,12 @@ mod execute {
 
     pub fn approve_social_recovery(
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         target: Addr,
     ) -> Result<Response, ContractError> {
         let state = STATE.load(deps.storage)?;
-        if !state.recovery_pool.contains(&info.sender)
+        if state.recovery_member(&info.sender).is_none()
             || state.owner == info.sender
         {
             // Owner cannot participate in social recovery, use TransferOwnership instead
@@ -381,232 +1116,3325 @@ mod execute {
             return Err(ContractError::Unauthorized {});
         }
         require_first_vote!(deps.storage, &info.sender);
-        if state.potential_owner == None
+        if state.potential_owner.is_none()
             || state.recovery_method != Some("recovery".to_string())
         {
             return Err(ContractError::NotInProgress {});
         }
+        if is_expired(&state, &env) {
+            return Err(ContractError::RecoveryExpired {});
+        }
         if state.potential_owner != Some(target) {
             return Err(ContractError::InvariantMismatch(
                 "Transfer account not matching submitted.".to_string(),
             ));
         }
-        _approve_recovery(deps, info, "recovery")
+        _approve_recovery(deps, env, info, "recovery")
     }
 
-    pub fn approve_transfer_ownership(
+    pub fn submit_recovery_approvals(
         deps: DepsMut,
-        info: MessageInfo,
-        target: Addr,
+        env: Env,
+        payload: Binary,
+        signatures: Vec<GuardianSignature>,
     ) -> Result<Response, ContractError> {
-        let state = STATE.load(deps.storage)?;
-        if !state.recovery_pool.contains(&info.sender) {
-            return Err(ContractError::Unauthorized {});
+        if signatures.is_empty() {
+            return Err(ContractError::NoSignatures {});
         }
-        require_first_vote!(deps.storage, &info.sender);
-        if state.potential_owner == None
-            || state.recovery_method != Some("transfer_ownership".to_string())
+        let mut state = STATE.load(deps.storage)?;
+        if state.potential_owner.is_some() && is_expired(&state, &env) {
+            // Approvals of the expired process must not start a new one
+            clear_recovery(deps.storage, &mut state)?;
+        }
+        let approval: RecoveryApproval = from_json(&payload)?;
+        if approval.chain_id != env.block.chain_id
+            || approval.host != env.contract.address
         {
-            return Err(ContractError::NotInProgress {});
+            return Err(ContractError::SignatureDomainMismatch {});
         }
-        if state.potential_owner != Some(target) {
-            return Err(ContractError::InvariantMismatch(
-                "Transfer account not matching submitted.".to_string(),
-            ));
+        if approval.recovery_nonce != state.recovery_nonce {
+            return Err(ContractError::StaleRecoveryApproval {});
+        }
+        if env.block.time >= approval.expires_at {
+            return Err(ContractError::SignatureExpired {});
         }
-        _approve_recovery(deps, info, "transfer_ownership")
-    }
-}
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetRecoveryPool {} => {
-            to_binary(&query::get_recovery_pool(deps)?)
+        let mut guardians: Vec<Addr> = vec![];
+        for GuardianSignature { guardian, signature } in signatures {
+            let member = state
+                .recovery_member(&guardian)
+                .filter(|m| m.addr != state.owner)
+                .ok_or(ContractError::Unauthorized {})?;
+            let pubkey = member
+                .pubkey
+                .as_ref()
+                .ok_or(ContractError::MissingPubkey {})?;
+            signed::verify_guardian(deps.api, pubkey, &payload, &signature)?;
+            if guardians.contains(&guardian) {
+                return Err(ContractError::AlreadyVoted {});
+            }
+            // Retracting must not be undone by replaying the signature,
+            // the guardian can still approve again on chain
+            let retracted =
+                RETRACTED_APPROVALS.may_load(deps.storage, &guardian)?;
+            if retracted == Some(state.recovery_nonce) {
+                return Err(ContractError::ApprovalRetracted {});
+            }
+            guardians.push(guardian);
         }
-        QueryMsg::GetSlaves {} => to_binary(&query::get_slaves(deps)?),
-        QueryMsg::GetSlave { chain } => {
-            to_binary(&query::get_slave(deps, chain)?)
+
+        let approvals = guardians.len();
+        let mut voters = guardians.into_iter();
+        match &state.potential_owner {
+            None => {
+                if approval.target_addr == state.owner {
+                    return Err(ContractError::SelfRecovery {});
+                }
+                let initiator = voters.next().expect("checked above");
+                start_recovery(
+                    deps.storage,
+                    &env,
+                    initiator,
+                    approval.target_addr,
+                    "recovery",
+                )?;
+            }
+            Some(target) => {
+                if state.recovery_method != Some("recovery".to_string()) {
+                    return Err(ContractError::NotInProgress {});
+                }
+                if *target != approval.target_addr {
+                    return Err(ContractError::InvariantMismatch(
+                        "Transfer account not matching submitted."
+                            .to_string(),
+                    ));
+                }
+            }
+        }
+        let voters: Vec<_> = voters.collect();
+        for guardian in voters.iter() {
+            require_first_vote!(deps.storage, guardian);
+        }
+        for guardian in voters.iter() {
+            ACTIVE_RECOVERY.push_back(deps.storage, guardian)?;
         }
-    }
-}
 
-pub mod query {
-    use std::collections::HashMap;
+        if can_transfer_ownership(&deps, "recovery")? {
+            return on_quorum(deps, &env, "recovery");
+        }
+        Ok(Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "submit_recovery_approvals")
+            .add_attribute("approvals", approvals.to_string()))
+    }
+
+    pub fn veto_recovery(
+        deps: DepsMut,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let mut state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        if state.recovery_method != Some("recovery".to_string()) {
+            return Err(ContractError::NotInProgress {});
+        }
+        clear_recovery(deps.storage, &mut state)?;
+        Ok(Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "veto_recovery"))
+    }
+
+    pub fn cancel_recovery(
+        deps: DepsMut,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let mut state = STATE.load(deps.storage)?;
+        if state.potential_owner.is_none() {
+            return Err(ContractError::NotInProgress {});
+        }
+        if info.sender != state.owner
+            && Some(&info.sender) != state.recovery_initiator.as_ref()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        clear_recovery(deps.storage, &mut state)?;
+        Ok(Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "cancel_recovery"))
+    }
+
+    pub fn retract_recovery_approval(
+        deps: DepsMut,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let mut state = STATE.load(deps.storage)?;
+        let Some(method) = state.recovery_method.clone() else {
+            return Err(ContractError::NotInProgress {});
+        };
+        let votes = ACTIVE_RECOVERY
+            .iter(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+        if !votes.contains(&info.sender) {
+            return Err(ContractError::NotVoted {});
+        }
+        let response = Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "retract_recovery_approval");
+        RETRACTED_APPROVALS.save(
+            deps.storage,
+            &info.sender,
+            &state.recovery_nonce,
+        )?;
+        if votes.len() == 1 {
+            // Nobody supports this process anymore
+            clear_recovery(deps.storage, &mut state)?;
+            return Ok(response.add_attribute("cancelled", "true"));
+        }
+
+        while let Ok(Some(_)) = ACTIVE_RECOVERY.pop_back(deps.storage) {}
+        for voter in votes.iter().filter(|v| **v != info.sender) {
+            ACTIVE_RECOVERY.push_back(deps.storage, voter)?;
+        }
+        if state.recovery_unlocks_at.is_some()
+            && !can_transfer_ownership(&deps, &method)?
+        {
+            state.recovery_unlocks_at = None;
+            STATE.save(deps.storage, &state)?;
+        }
+        Ok(response)
+    }
+
+    pub fn finalize_recovery(
+        deps: DepsMut,
+        env: Env,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        match state.recovery_unlocks_at {
+            None => Err(ContractError::NotInProgress {}),
+            Some(unlocks_at) if env.block.time < unlocks_at => {
+                Err(ContractError::RecoveryLocked {})
+            }
+            Some(_) => do_transfer_ownership(deps, &env),
+        }
+    }
+
+    pub fn update_recovery_delay(
+        deps: DepsMut,
+        info: MessageInfo,
+        delay: u64,
+    ) -> Result<Response, ContractError> {
+        if delay > MAX_RECOVERY_DELAY {
+            return Err(ContractError::RecoveryDelayTooLong {
+                max: MAX_RECOVERY_DELAY,
+            });
+        }
+        STATE.update(deps.storage, |mut state| {
+            require_owner!(info, state);
+            if state.potential_owner.is_some() {
+                return Err(ContractError::AlreadyRecovering {});
+            }
+            state.recovery_delay = delay;
+            Ok(state)
+        })?;
+        Ok(Response::new()
+            .add_attribute("action", "update_recovery_delay")
+            .add_attribute("delay", delay.to_string()))
+    }
+
+    pub fn update_recovery_lifetime(
+        deps: DepsMut,
+        info: MessageInfo,
+        lifetime: u64,
+    ) -> Result<Response, ContractError> {
+        if lifetime == 0 {
+            return Err(ContractError::InvalidPeriod {});
+        }
+        STATE.update(deps.storage, |mut state| {
+            require_owner!(info, state);
+            state.recovery_lifetime = lifetime;
+            Ok(state)
+        })?;
+        Ok(Response::new()
+            .add_attribute("action", "update_recovery_lifetime")
+            .add_attribute("lifetime", lifetime.to_string()))
+    }
+
+    pub fn approve_transfer_ownership(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        target: Addr,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        if state.recovery_member(&info.sender).is_none() {
+            return Err(ContractError::Unauthorized {});
+        }
+        require_first_vote!(deps.storage, &info.sender);
+        if state.potential_owner.is_none()
+            || state.recovery_method != Some("transfer_ownership".to_string())
+        {
+            return Err(ContractError::NotInProgress {});
+        }
+        if is_expired(&state, &env) {
+            return Err(ContractError::RecoveryExpired {});
+        }
+        if state.potential_owner != Some(target) {
+            return Err(ContractError::InvariantMismatch(
+                "Transfer account not matching submitted.".to_string(),
+            ));
+        }
+        _approve_recovery(deps, env, info, "transfer_ownership")
+    }
+
+    fn samechain_slave(
+        deps: &DepsMut,
+        state: &State,
+    ) -> Result<Addr, ContractError> {
+        SLAVES
+            .may_load(deps.storage, &state.chain)?
+            .map(|slave| slave.addr)
+            .ok_or(ContractError::ChainNotRegistered {})
+    }
+
+    fn create_proposal(
+        deps: DepsMut,
+        env: &Env,
+        state: &State,
+        proposer: Addr,
+        msgs: Vec<CosmosMsg>,
+        expires_in: Option<u64>,
+    ) -> Result<u64, ContractError> {
+        if state.approval_pool.is_empty() {
+            return Err(ContractError::EmptyApprovalPool {});
+        }
+        if msgs.is_empty() {
+            return Err(ContractError::EmptyProposal {});
+        }
+
+        let id = PROPOSAL_COUNT.load(deps.storage)? + 1;
+        PROPOSAL_COUNT.save(deps.storage, &id)?;
+        let proposal = Proposal {
+            id,
+            proposer,
+            msgs,
+            approvals: vec![],
+            status: ProposalStatus::Open,
+            expires_at: env.block.time.plus_seconds(
+                expires_in.unwrap_or(DEFAULT_PROPOSAL_LIFETIME),
+            ),
+        };
+        PROPOSALS.save(deps.storage, id, &proposal)?;
+        Ok(id)
+    }
+
+    pub fn propose_transaction(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msgs: Vec<CosmosMsg>,
+        expires_in: Option<u64>,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        // Fail early instead of accepting a proposal that can never run.
+        samechain_slave(&deps, &state)?;
+        let id = create_proposal(
+            deps,
+            &env,
+            &state,
+            info.sender,
+            msgs,
+            expires_in,
+        )?;
+
+        Ok(Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "propose_transaction")
+            .add_attribute("proposal_id", id.to_string()))
+    }
+
+    pub fn approve_proposal(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        proposal_id: u64,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        if !state.approval_pool.contains(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        let mut proposal = PROPOSALS.load(deps.storage, proposal_id)?;
+        if proposal.status != ProposalStatus::Open {
+            return Err(ContractError::ProposalNotOpen {});
+        }
+        if env.block.time >= proposal.expires_at {
+            return Err(ContractError::ProposalExpired {});
+        }
+        if proposal.approvals.contains(&info.sender) {
+            return Err(ContractError::AlreadyVoted {});
+        }
+        proposal.approvals.push(info.sender);
+
+        let response = Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "approve_proposal")
+            .add_attribute("proposal_id", proposal_id.to_string());
+        // Approvals of members removed since then no longer count
+        let approvals = proposal
+            .approvals
+            .iter()
+            .filter(|member| state.approval_pool.contains(member))
+            .count() as u32;
+        if approvals < state.proposal_approvals_needed {
+            PROPOSALS.save(deps.storage, proposal_id, &proposal)?;
+            return Ok(response);
+        }
+
+        let slave_contract = samechain_slave(&deps, &state)?;
+        proposal.status = ProposalStatus::Executed;
+        PROPOSALS.save(deps.storage, proposal_id, &proposal)?;
+        spending::spend_unchecked(
+            deps.storage,
+            env.block.time,
+            &proposal.msgs,
+        )?;
+        let (tx_id, sub_msg) = dispatch(
+            deps.storage,
+            &env,
+            &proposal.proposer,
+            &slave_contract,
+            proposal.msgs,
+            BatchMode::Atomic,
+            vec![],
+        )?;
+        Ok(response
+            .add_attribute("executed", "true")
+            .add_attribute("tx_id", tx_id.to_string())
+            .add_submessage(sub_msg))
+    }
+
+    pub fn cancel_proposal(
+        deps: DepsMut,
+        info: MessageInfo,
+        proposal_id: u64,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        let mut proposal = PROPOSALS.load(deps.storage, proposal_id)?;
+        if proposal.status != ProposalStatus::Open {
+            return Err(ContractError::ProposalNotOpen {});
+        }
+        proposal.status = ProposalStatus::Cancelled;
+        PROPOSALS.save(deps.storage, proposal_id, &proposal)?;
+        Ok(Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "cancel_proposal")
+            .add_attribute("proposal_id", proposal_id.to_string()))
+    }
+
+    pub fn update_proposal_approvals_needed(
+        deps: DepsMut,
+        info: MessageInfo,
+        approvals_needed: u32,
+    ) -> Result<Response, ContractError> {
+        STATE.update(deps.storage, |mut state| {
+            require_owner!(info, state);
+            state.proposal_approvals_needed = approvals_needed;
+            validate_approvals_needed(&state)?;
+            Ok(state)
+        })?;
+        Ok(Response::new()
+            .add_attribute("action", "update_proposal_approvals_needed"))
+    }
+
+    /// An empty pool keeps a threshold of 1, proposals are refused until
+    /// members are added.
+    pub fn validate_approvals_needed(
+        state: &State,
+    ) -> Result<(), ContractError> {
+        let max = (state.approval_pool.len() as u32).max(1);
+        if !(1..=max).contains(&state.proposal_approvals_needed) {
+            return Err(ContractError::InvalidApprovalsNeeded { max });
+        }
+        Ok(())
+    }
+
+    pub fn set_spending_limit(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        denom: String,
+        period: u64,
+        amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        if period == 0 {
+            return Err(ContractError::InvalidPeriod {});
+        }
+        let response = Response::new()
+            .add_attribute("action", "set_spending_limit")
+            .add_attribute("denom", &denom)
+            .add_attribute("period", period.to_string())
+            .add_attribute("amount", amount);
+        let current =
+            SPENDING_LIMITS.may_load(deps.storage, (&denom, period))?;
+        if current.is_some_and(|current| amount > current) {
+            let pending = PendingLimit {
+                amount: Some(amount),
+                effective_at: env
+                    .block
+                    .time
+                    .plus_seconds(LIMIT_LOOSENING_DELAY),
+            };
+            PENDING_LIMITS.save(deps.storage, (&denom, period), &pending)?;
+            return Ok(response.add_attribute(
+                "effective_at",
+                pending.effective_at.to_string(),
+            ));
+        }
+        // Also drops a loosening still waiting for its timelock
+        PENDING_LIMITS.remove(deps.storage, (&denom, period));
+        SPENDING_LIMITS.save(deps.storage, (&denom, period), &amount)?;
+        Ok(response)
+    }
+
+    pub fn remove_spending_limit(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        denom: String,
+        period: u64,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        if !SPENDING_LIMITS.has(deps.storage, (&denom, period)) {
+            return Err(ContractError::Std(StdError::not_found(
+                "Spending limit",
+            )));
+        }
+        let pending = PendingLimit {
+            amount: None,
+            effective_at: env.block.time.plus_seconds(LIMIT_LOOSENING_DELAY),
+        };
+        PENDING_LIMITS.save(deps.storage, (&denom, period), &pending)?;
+        Ok(Response::new()
+            .add_attribute("action", "remove_spending_limit")
+            .add_attribute("denom", denom)
+            .add_attribute("period", period.to_string())
+            .add_attribute("effective_at", pending.effective_at.to_string()))
+    }
+
+    pub fn update_limit_exceeded_action(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        action: LimitExceededAction,
+    ) -> Result<Response, ContractError> {
+        let mut state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        let response = Response::new()
+            .add_attribute("action", "update_limit_exceeded_action");
+        // Escalated transactions only need the owner-managed approval pool
+        if action == LimitExceededAction::Escalate
+            && state.limit_exceeded_action == LimitExceededAction::Reject
+        {
+            let effective_at =
+                env.block.time.plus_seconds(LIMIT_LOOSENING_DELAY);
+            PENDING_ESCALATION.save(deps.storage, &effective_at)?;
+            return Ok(response
+                .add_attribute("effective_at", effective_at.to_string()));
+        }
+        PENDING_ESCALATION.remove(deps.storage);
+        state.limit_exceeded_action = action;
+        STATE.save(deps.storage, &state)?;
+        Ok(response)
+    }
+
+    pub fn apply_limit_changes(
+        deps: DepsMut,
+        env: Env,
+    ) -> Result<Response, ContractError> {
+        let due = PENDING_LIMITS
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter(|item| {
+                item.as_ref().map_or(true, |(_, pending)| {
+                    pending.effective_at <= env.block.time
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        let mut applied = due.len();
+        for ((denom, period), pending) in due {
+            let key = (denom.as_str(), period);
+            PENDING_LIMITS.remove(deps.storage, key);
+            match pending.amount {
+                Some(amount) => {
+                    SPENDING_LIMITS.save(deps.storage, key, &amount)?
+                }
+                None => SPENDING_LIMITS.remove(deps.storage, key),
+            }
+        }
+        let escalation = PENDING_ESCALATION.may_load(deps.storage)?;
+        if escalation.is_some_and(|at| at <= env.block.time) {
+            PENDING_ESCALATION.remove(deps.storage);
+            STATE.update(deps.storage, |mut state| {
+                state.limit_exceeded_action = LimitExceededAction::Escalate;
+                StdResult::Ok(state)
+            })?;
+            applied += 1;
+        }
+        Ok(Response::new()
+            .add_attribute("action", "apply_limit_changes")
+            .add_attribute("applied", applied.to_string()))
+    }
+
+    pub fn execute_signed(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        payload: Binary,
+        signature: Binary,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        let signed = signed::verify(
+            deps.api,
+            deps.storage,
+            &env,
+            &state,
+            &payload,
+            &signature,
+        )?;
+        let slave_contract = samechain_slave(&deps, &state)?;
+        let mut msgs = signed.msgs;
+        if let Some(fee) = signed.relayer_fee {
+            msgs.push(
+                BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: vec![fee],
+                }
+                .into(),
+            );
+        }
+        // Nobody is around to approve, so limits are never escalated here
+        spending::spend(deps.storage, env.block.time, &msgs)?;
+        let (tx_id, sub_msg) = dispatch(
+            deps.storage,
+            &env,
+            &state.owner,
+            &slave_contract,
+            msgs,
+            BatchMode::Atomic,
+            vec![],
+        )?;
+        Ok(Response::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "execute_signed")
+            .add_attribute("nonce", signed.nonce.to_string())
+            .add_attribute("relayer", info.sender)
+            .add_attribute("tx_id", tx_id.to_string())
+            .add_submessage(sub_msg))
+    }
+
+    pub fn update_owner_pubkey(
+        deps: DepsMut,
+        info: MessageInfo,
+        pubkey: Option<Binary>,
+    ) -> Result<Response, ContractError> {
+        if let Some(pubkey) = &pubkey {
+            signed::validate_pubkey(pubkey)?;
+        }
+        STATE.update(deps.storage, |mut state| {
+            require_owner!(info, state);
+            state.owner_pubkey = pubkey;
+            Ok::<_, ContractError>(state)
+        })?;
+        Ok(Response::new().add_attribute("action", "update_owner_pubkey"))
+    }
+
+    pub fn add_session_key(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        key: Addr,
+        expires_in: u64,
+        session: SessionKey,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        let key = deps.api.addr_validate(key.as_str())?;
+        if SESSION_KEYS.has(deps.storage, &key) {
+            return Err(ContractError::MemberAlreadyAdded {});
+        }
+        let session = SessionKey {
+            expires_at: env.block.time.plus_seconds(expires_in),
+            ..session
+        };
+        SESSION_KEYS.save(deps.storage, &key, &session)?;
+        Ok(Response::new()
+            .add_attribute("action", "add_session_key")
+            .add_attribute("key", key)
+            .add_attribute("expires_at", session.expires_at.to_string()))
+    }
+
+    pub fn revoke_session_key(
+        deps: DepsMut,
+        info: MessageInfo,
+        key: Addr,
+    ) -> Result<Response, ContractError> {
+        let state = STATE.load(deps.storage)?;
+        require_owner!(info, state);
+        if !SESSION_KEYS.has(deps.storage, &key) {
+            return Err(ContractError::MemberNotFound {});
+        }
+        SESSION_KEYS.remove(deps.storage, &key);
+        Ok(Response::new()
+            .add_attribute("action", "revoke_session_key")
+            .add_attribute("key", key))
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(
+    deps: DepsMut,
+    _env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let reply_kind = ReplyKind::try_from(msg.id);
+    if reply_kind.is_err() {
+        return Err(ContractError::UnknownReplyID(msg.id));
+    }
+
+    match reply_kind.unwrap() {
+        ReplyKind::ReplyExecuteBatch => {
+            let response = Response::new()
+                .add_attribute("contract", "host")
+                .add_attribute("method", "reply_execute_batch");
+            let result = match msg.result {
+                SubMsgResult::Ok(result) => result,
+                // Slave changes are reverted, but the attempt is kept
+                SubMsgResult::Err(err) => {
+                    let tx = history::finish(
+                        deps.storage,
+                        TxStatus::Failed,
+                        vec![],
+                        Some(err),
+                    )?;
+                    let response = response
+                        .add_attribute("tx_id", tx.id.to_string())
+                        .add_attribute("status", "failed");
+                    return release(deps.storage, &tx, response);
+                }
+            };
+            let data = match result.data {
+                Some(data) => {
+                    parse_execute_response_data(&data)
+                        .map_err(|e| StdError::generic_err(e.to_string()))?
+                        .data
+                }
+                None => None,
+            };
+            let batch: Option<BatchResult> =
+                data.as_ref().map(from_json).transpose()?;
+            let failed_msgs = batch
+                .map(|batch| batch.failed.iter().map(|f| f.index).collect())
+                .unwrap_or_default();
+            let tx = history::finish(
+                deps.storage,
+                TxStatus::Succeeded,
+                failed_msgs,
+                None,
+            )?;
+            RESERVATIONS.remove(deps.storage, tx.id);
+            let response = response
+                .add_attribute("tx_id", tx.id.to_string())
+                .add_attribute("status", "succeeded")
+                .add_attribute("failed", tx.failed_msgs.len().to_string());
+            match data {
+                Some(data) => Ok(response.set_data(data)),
+                None => Ok(response),
+            }
+        }
+    }
+}
+
+/// Undo what the host charged for transaction `tx` after the slave failed:
+/// spending limits, session key cap and attached funds.
+fn release(
+    storage: &mut dyn Storage,
+    tx: &Transaction,
+    response: Response,
+) -> Result<Response, ContractError> {
+    spending::release(storage, tx.time, &tx.funds)?;
+    let Some(reservation) = RESERVATIONS.may_load(storage, tx.id)? else {
+        return Ok(response);
+    };
+    RESERVATIONS.remove(storage, tx.id);
+    if let Some(key) = &reservation.session_key {
+        session::release(storage, key, &tx.funds)?;
+    }
+    if reservation.funds.is_empty() {
+        return Ok(response);
+    }
+    Ok(response.add_message(BankMsg::Send {
+        to_address: tx.sender.to_string(),
+        amount: reservation.funds,
+    }))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetRecoveryPool {} => {
+            to_json_binary(&query::get_recovery_pool(deps)?)
+        }
+        QueryMsg::GetSlaves { start_after, limit } => {
+            to_json_binary(&query::get_slaves(deps, start_after, limit)?)
+        }
+        QueryMsg::GetSlave { chain } => {
+            to_json_binary(&query::get_slave(deps, chain)?)
+        }
+        QueryMsg::GetSlaveSyncStatus { chain } => {
+            to_json_binary(&query::get_slave_sync_status(deps, chain)?)
+        }
+        QueryMsg::GetProposal { proposal_id } => {
+            to_json_binary(&query::get_proposal(deps, env, proposal_id)?)
+        }
+        QueryMsg::ListProposals { start_after, limit } => to_json_binary(
+            &query::list_proposals(deps, env, start_after, limit)?,
+        ),
+        QueryMsg::GetSpendingAllowance { denom } => {
+            to_json_binary(&query::get_spending_allowance(deps, env, denom)?)
+        }
+        QueryMsg::GetTransactions { start_after, limit } => to_json_binary(
+            &query::get_transactions(deps, start_after, limit)?,
+        ),
+        QueryMsg::GetNonce { nonce } => {
+            to_json_binary(&query::get_nonce(deps, nonce)?)
+        }
+        QueryMsg::GetSessionKey { key } => {
+            to_json_binary(&query::get_session_key(deps, key)?)
+        }
+        QueryMsg::ListSessionKeys { start_after, limit } => to_json_binary(
+            &query::list_session_keys(deps, start_after, limit)?,
+        ),
+    }
+}
+
+pub mod query {
+    use crate::msg::{
+        GetRecoveryPoolResponse, GetSlaveResponse, GetSlavesResponse,
+        GetSpendingAllowanceResponse, GetTransactionsResponse,
+        ListProposalsResponse, ListSessionKeysResponse, NonceResponse,
+        PendingLimitChange, ProposalResponse, SessionKeyResponse,
+        SlaveResponse, SlaveSyncStatusResponse,
+    };
+
+    use super::*;
+
+    pub fn get_slaves(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<GetSlavesResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let slaves = SLAVES
+            .range(
+                deps.storage,
+                start_after.as_deref().map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|item| {
+                item.map(|(chain, slave)| SlaveResponse {
+                    chain,
+                    addr: slave.addr,
+                    kind: slave.kind,
+                    code_version: slave.code_version,
+                    registered_at: slave.registered_at,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(GetSlavesResponse { slaves })
+    }
+
+    pub fn get_recovery_pool(
+        deps: Deps,
+    ) -> StdResult<GetRecoveryPoolResponse> {
+        let state = STATE.load(deps.storage)?;
+        let recovery_progress = if state.recovery_method.is_some() {
+            let voters = ACTIVE_RECOVERY
+                .iter(deps.storage)?
+                .collect::<StdResult<Vec<_>>>()?;
+            state.approvals_weight(&voters)
+        } else {
+            0
+        };
+        Ok(GetRecoveryPoolResponse {
+            members: state.recovery_pool,
+            recovery_approvals_count: state.recovery_approvals_needed,
+            transfer_approvals_count: state
+                .transfer_ownership_approvals_needed,
+            recovery_progress,
+            recovery_method: state.recovery_method,
+            new_owner: state.potential_owner,
+            recovery_initiator: state.recovery_initiator,
+            recovery_expires_at: state.recovery_expires_at,
+            recovery_delay: state.recovery_delay,
+            recovery_unlocks_at: state.recovery_unlocks_at,
+            recovery_nonce: state.recovery_nonce,
+        })
+    }
+
+    pub fn get_slave(
+        deps: Deps,
+        chain: String,
+    ) -> StdResult<GetSlaveResponse> {
+        let slave = SLAVES.may_load(deps.storage, &chain)?;
+        Ok(GetSlaveResponse { slave: slave.map(|slave| slave.addr) })
+    }
+
+    pub fn get_slave_sync_status(
+        deps: Deps,
+        chain: String,
+    ) -> StdResult<SlaveSyncStatusResponse> {
+        let version =
+            CONFIG_VERSION.may_load(deps.storage)?.unwrap_or_default();
+        let synced_version = SYNCED_CONFIGS.may_load(deps.storage, &chain)?;
+        Ok(SlaveSyncStatusResponse {
+            version,
+            synced_version,
+            in_sync: synced_version == Some(version),
+        })
+    }
+
+    fn proposal_response(
+        env: &Env,
+        state: &State,
+        proposal: Proposal,
+    ) -> ProposalResponse {
+        let status = if proposal.status == ProposalStatus::Open
+            && env.block.time >= proposal.expires_at
+        {
+            ProposalStatus::Expired
+        } else {
+            proposal.status
+        };
+        ProposalResponse {
+            id: proposal.id,
+            proposer: proposal.proposer,
+            msgs: proposal.msgs,
+            approvals: proposal.approvals,
+            approvals_needed: state.proposal_approvals_needed,
+            status,
+            expires_at: proposal.expires_at,
+        }
+    }
+
+    pub fn get_proposal(
+        deps: Deps,
+        env: Env,
+        proposal_id: u64,
+    ) -> StdResult<ProposalResponse> {
+        let state = STATE.load(deps.storage)?;
+        let proposal = PROPOSALS.load(deps.storage, proposal_id)?;
+        Ok(proposal_response(&env, &state, proposal))
+    }
+
+    pub fn list_proposals(
+        deps: Deps,
+        env: Env,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<ListProposalsResponse> {
+        let state = STATE.load(deps.storage)?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let proposals = PROPOSALS
+            .range(
+                deps.storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|item| {
+                item.map(|(_, proposal)| {
+                    proposal_response(&env, &state, proposal)
+                })
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(ListProposalsResponse { proposals })
+    }
+
+    pub fn get_spending_allowance(
+        deps: Deps,
+        env: Env,
+        denom: Option<String>,
+    ) -> StdResult<GetSpendingAllowanceResponse> {
+        let state = STATE.load(deps.storage)?;
+        Ok(GetSpendingAllowanceResponse {
+            allowances: spending::allowances(
+                deps.storage,
+                env.block.time,
+                denom,
+            )?,
+            limit_exceeded_action: state.limit_exceeded_action,
+            pending_limits: PENDING_LIMITS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| {
+                    item.map(|((denom, period), pending)| {
+                        PendingLimitChange {
+                            denom,
+                            period,
+                            amount: pending.amount,
+                            effective_at: pending.effective_at,
+                        }
+                    })
+                })
+                .collect::<StdResult<_>>()?,
+            pending_escalation: PENDING_ESCALATION.may_load(deps.storage)?,
+        })
+    }
+
+    pub fn get_transactions(
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<GetTransactionsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let transactions = TRANSACTIONS
+            .range(
+                deps.storage,
+                None,
+                start_after.map(Bound::exclusive),
+                Order::Descending,
+            )
+            .take(limit)
+            .map(|item| item.map(|(_, tx)| tx))
+            .collect::<StdResult<_>>()?;
+        Ok(GetTransactionsResponse { transactions })
+    }
+
+    pub fn get_nonce(deps: Deps, nonce: u64) -> StdResult<NonceResponse> {
+        let last = LAST_NONCE.may_load(deps.storage)?.unwrap_or_default();
+        let used = nonce <= last;
+        Ok(NonceResponse { nonce, used })
+    }
+
+    fn session_key_response(
+        key: Addr,
+        session: SessionKey,
+    ) -> SessionKeyResponse {
+        SessionKeyResponse {
+            key,
+            expires_at: session.expires_at,
+            spend_limit: session.spend_limit,
+            spent: session.spent,
+            allowed_msgs: session.allowed_msgs,
+            allowed_contracts: session.allowed_contracts,
+        }
+    }
+
+    pub fn get_session_key(
+        deps: Deps,
+        key: Addr,
+    ) -> StdResult<SessionKeyResponse> {
+        let session = SESSION_KEYS.load(deps.storage, &key)?;
+        Ok(session_key_response(key, session))
+    }
+
+    pub fn list_session_keys(
+        deps: Deps,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<ListSessionKeysResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let keys = SESSION_KEYS
+            .range(
+                deps.storage,
+                start_after.as_ref().map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|item| {
+                item.map(|(key, session)| session_key_response(key, session))
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(ListSessionKeysResponse { keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{
+        BatchFailure, ChainInfo, ChainResponse, SignedPayload, SlaveContract,
+    };
+    use crate::state::MsgKind;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier,
+        MockStorage, MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        coins, Coin, ContractInfoResponse, ContractResult, Empty, OwnedDeps,
+        Querier, QuerierResult, QueryRequest, SystemError, SystemResult,
+        WasmQuery,
+    };
+
+    fn member(info: &MessageInfo, weight: u32) -> RecoveryMember {
+        RecoveryMember {
+            addr: info.sender.clone(),
+            weight,
+            required: false,
+            pubkey: None,
+            chain: None,
+        }
+    }
+
+    /// Dependencies where "creator" is a master serving slave code 2 for
+    /// "foo-1" and "bar-1", the latter reached over "connection-2", and
+    /// every contract but "stranger" is one of its instances. Interchain
+    /// accounts are opened on "hub-4" over "connection-3".
+    fn mock_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::ContractInfo { contract_addr }
+                if contract_addr != "stranger" =>
+            {
+                let mut info = ContractInfoResponse::default();
+                info.code_id = 2;
+                info.creator = "creator".to_string();
+                SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&info).unwrap(),
+                ))
+            }
+            WasmQuery::Smart { contract_addr, msg }
+                if contract_addr == "creator" =>
+            {
+                let res = match from_json(msg).unwrap() {
+                    MasterQueryMsg::GetSlaveCodeId { chain } => {
+                        let code_id = ["foo-1", "bar-1"]
+                            .contains(&chain.as_str())
+                            .then_some(2);
+                        to_json_binary(&GetSlaveCodeIdResponse { code_id })
+                    }
+                    MasterQueryMsg::GetSlaveHost { slave } => {
+                        let host = match slave.as_str() {
+                            "foreign" => "otherhost",
+                            _ => MOCK_CONTRACT_ADDR,
+                        };
+                        let slave = SlaveContract {
+                            host: Addr::unchecked(host),
+                            chain: "foo-1".to_string(),
+                            address: slave,
+                        };
+                        to_json_binary(&SlaveHostResponse {
+                            slave: Some(slave),
+                        })
+                    }
+                    MasterQueryMsg::GetChain { chain_id } => {
+                        let remote = match chain_id.as_str() {
+                            "bar-1" => Some((
+                                "bar",
+                                "connection-2",
+                                ChainSlaveKind::WasmIbc,
+                            )),
+                            "hub-4" => Some((
+                                "hub",
+                                "connection-3",
+                                ChainSlaveKind::Ica,
+                            )),
+                            _ => None,
+                        };
+                        let chain =
+                            remote.map(|(prefix, connection, kind)| {
+                                ChainInfo {
+                                    chain_id,
+                                    bech32_prefix: prefix.to_string(),
+                                    connection_id: Some(
+                                        connection.to_string(),
+                                    ),
+                                    transfer_channel_id: None,
+                                    fee_denom: format!("u{prefix}"),
+                                    slave_kind: kind,
+                                }
+                            });
+                        to_json_binary(&ChainResponse { chain })
+                    }
+                };
+                SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::NoSuchContract {
+                addr: "unknown".to_string(),
+            }),
+        });
+        deps
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn recovery() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info("creator", &coins(2, "token"));
+        let creator = info.sender.clone();
+        let info_a = mock_info("a", &coins(2, "token"));
+        let info_b = mock_info("b", &coins(2, "token"));
+        let info_new_owner = mock_info("new_owner", &coins(2, "token"));
+        let new_owner = info_new_owner.sender.clone();
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![member(&info_a, 1), member(&info_b, 1)],
+            approval_pool: vec![],
+            recovery_approvals_needed: 2,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: creator.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::BeginSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_a.clone(), msg).unwrap();
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, Some(new_owner.clone()));
+        assert_eq!(state.owner, creator);
+
+        let msg = ExecuteMsg::ApproveSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::AlreadyVoted {});
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, Some(new_owner.clone()));
+        assert_eq!(state.owner, creator);
+
+        let msg = ExecuteMsg::ApproveSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, Some(new_owner.clone()));
+        assert_eq!(state.owner, creator);
+
+        let msg = ExecuteMsg::FinalizeRecovery {};
+        let res = execute(deps.as_mut(), mock_env(), info_b.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RecoveryLocked {});
+
+        let mut later = mock_env();
+        later.block.time =
+            later.block.time.plus_seconds(state.recovery_delay);
+        let msg = ExecuteMsg::FinalizeRecovery {};
+        execute(deps.as_mut(), later, info_new_owner.clone(), msg).unwrap();
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, None);
+        assert_eq!(state.recovery_unlocks_at, None);
+        assert_eq!(state.owner, new_owner.clone());
+    }
+
+    #[test]
+    fn recovery_veto() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info("creator", &[]);
+        let info_a = mock_info("a", &[]);
+        let new_owner = Addr::unchecked("new_owner");
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![member(&info_a, 1)],
+            approval_pool: vec![],
+            recovery_approvals_needed: 1,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: Some(60),
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::BeginSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_a.clone(), msg).unwrap();
+        let res = query::get_recovery_pool(deps.as_ref()).unwrap();
+        assert_eq!(
+            res.recovery_unlocks_at,
+            Some(mock_env().block.time.plus_seconds(60))
+        );
+
+        let msg = ExecuteMsg::VetoRecovery {};
+        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let msg = ExecuteMsg::VetoRecovery {};
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(60);
+        let msg = ExecuteMsg::FinalizeRecovery {};
+        let res = execute(deps.as_mut(), later, info_a.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::NotInProgress {});
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, None);
+        assert_eq!(state.owner, info.sender);
+    }
+
+    #[test]
+    fn transfer_ownership() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info("creator", &coins(2, "token"));
+        let creator = info.sender.clone();
+        let info_a = mock_info("a", &coins(2, "token"));
+        let info_b = mock_info("b", &coins(2, "token"));
+        let info_new_owner = mock_info("new_owner", &coins(2, "token"));
+        let new_owner = info_new_owner.sender.clone();
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![member(&info_a, 1), member(&info_b, 1)],
+            approval_pool: vec![],
+            recovery_approvals_needed: 2,
+            transfer_ownership_approvals_needed: 2,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: creator.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::BeginTransferOwnership {
+            target_addr: creator.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::SelfRecovery {});
+
+        let msg = ExecuteMsg::BeginTransferOwnership {
+            target_addr: new_owner.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg = ExecuteMsg::BeginTransferOwnership {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, Some(new_owner.clone()));
+        assert_eq!(state.owner, creator);
+
+        let msg = ExecuteMsg::ApproveTransferOwnership {
+            target_addr: new_owner.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, Some(new_owner.clone()));
+        assert_eq!(state.owner, creator);
+
+        let msg = ExecuteMsg::ApproveTransferOwnership {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_a.clone(), msg).unwrap();
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, Some(new_owner.clone()));
+        assert_eq!(state.owner, creator);
+
+        let msg = ExecuteMsg::ApproveTransferOwnership {
+            target_addr: new_owner.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::AlreadyVoted {});
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, Some(new_owner.clone()));
+        assert_eq!(state.owner, creator);
+
+        let msg = ExecuteMsg::ApproveTransferOwnership {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, None);
+        assert_eq!(state.owner, new_owner.clone());
+    }
+
+    #[test]
+    fn proposals() {
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let info_a = mock_info("a", &[]);
+        let info_b = mock_info("b", &[]);
+        let info_slave = mock_info("slave", &[]);
+
+        // An empty pool still needs one approval
+        let mut msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: Some(0),
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        let err =
+            instantiate(deps.as_mut(), mock_env(), info.clone(), msg.clone())
+                .unwrap_err();
+        assert_eq!(err, ContractError::InvalidApprovalsNeeded { max: 1 });
+        msg.proposal_approvals_needed = None;
+        let mut empty = mock_deps();
+        instantiate(empty.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let state = STATE.load(&empty.storage).unwrap();
+        assert_eq!(1, state.proposal_approvals_needed);
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![info_a.sender.clone(), info_b.sender.clone()],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info_slave.sender.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_slave.clone(), msg).unwrap();
+
+        let transfer: CosmosMsg = cosmwasm_std::BankMsg::Send {
+            to_address: "friend".to_string(),
+            amount: coins(5, "token"),
+        }
+        .into();
+        let msg = ExecuteMsg::ProposeTransaction {
+            msgs: vec![transfer.clone()],
+            expires_in: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg = ExecuteMsg::ProposeTransaction {
+            msgs: vec![transfer.clone()],
+            expires_in: Some(100),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::ApproveProposal { proposal_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg = ExecuteMsg::ApproveProposal { proposal_id: 1 };
+        let res =
+            execute(deps.as_mut(), mock_env(), info_a.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::ApproveProposal { proposal_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::AlreadyVoted {});
+
+        let mut late_env = mock_env();
+        late_env.block.time = late_env.block.time.plus_seconds(100);
+        let res =
+            query::get_proposal(deps.as_ref(), late_env.clone(), 1).unwrap();
+        assert_eq!(res.status, ProposalStatus::Expired);
+        let msg = ExecuteMsg::ApproveProposal { proposal_id: 1 };
+        let res = execute(deps.as_mut(), late_env, info_b.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ProposalExpired {});
+
+        for approvals_needed in [0, 3] {
+            let msg = ExecuteMsg::UpdateProposalApprovalsNeeded {
+                approvals_needed,
+            };
+            let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+            assert_eq!(
+                res.unwrap_err(),
+                ContractError::InvalidApprovalsNeeded { max: 2 }
+            );
+        }
+
+        // The approval of a removed member no longer counts
+        let info_c = mock_info("c", &[]);
+        let msg = ExecuteMsg::RemoveApprovalMember {
+            member: info_a.sender.clone(),
+        };
+        // The pool can't shrink below the threshold
+        let err =
+            execute(deps.as_mut(), mock_env(), info.clone(), msg.clone())
+                .unwrap_err();
+        assert_eq!(err, ContractError::InvalidApprovalsNeeded { max: 1 });
+        let add =
+            ExecuteMsg::AddApprovalMember { member: info_c.sender.clone() };
+        execute(deps.as_mut(), mock_env(), info.clone(), add).unwrap();
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::ApproveProposal { proposal_id: 1 };
+        let res =
+            execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::ApproveProposal { proposal_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), info_c, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            WasmMsg::Execute {
+                contract_addr: info_slave.sender.to_string(),
+                msg: to_json_binary(&SlaveExecuteMsg::ExecuteBatch {
+                    msgs: vec![transfer],
+                    mode: BatchMode::Atomic,
+                })
+                .unwrap(),
+                funds: vec![],
+            }
+            .into()
+        );
+        let res = query::get_proposal(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(res.status, ProposalStatus::Executed);
+
+        let msg = ExecuteMsg::CancelProposal { proposal_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ProposalNotOpen {});
+    }
+
+    #[test]
+    fn spending_limits() {
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let info_a = mock_info("a", &[]);
+        let info_slave = mock_info("slave", &[]);
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![info_a.sender.clone()],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info_slave.sender.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_slave.clone(), msg).unwrap();
+
+        let day = 24 * 60 * 60;
+        let msg = ExecuteMsg::SetSpendingLimit {
+            denom: "token".to_string(),
+            period: day,
+            amount: Uint128::new(100),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let send = |amount| ExecuteMsg::ExecuteSameChain {
+            body_proxy: cosmwasm_std::BankMsg::Send {
+                to_address: "friend".to_string(),
+                amount: coins(amount, "token"),
+            }
+            .into(),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), send(60)).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), send(50));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::SpendingLimitExceeded {
+                denom: "token".to_string()
+            }
+        );
+        let res =
+            query::get_spending_allowance(deps.as_ref(), mock_env(), None)
+                .unwrap();
+        assert_eq!(res.allowances[0].spent, Uint128::new(60));
+        assert_eq!(res.allowances[0].remaining, Uint128::new(40));
+
+        let msg = ExecuteMsg::ExecuteSameChain {
+            body_proxy: CosmosMsg::Stargate {
+                type_url: "/cosmos.bank.v1beta1.MsgSend".to_string(),
+                value: Binary::default(),
+            },
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::UncheckableMessage {});
+
+        // Sums overflowing the counters are rejected, not wrapped
+        let res =
+            execute(deps.as_mut(), mock_env(), info.clone(), send(u128::MAX));
+        assert!(matches!(
+            res.unwrap_err(),
+            ContractError::Std(StdError::Overflow { .. })
+        ));
+
+        let mut next_day = mock_env();
+        next_day.block.time = next_day.block.time.plus_seconds(day);
+        execute(deps.as_mut(), next_day.clone(), info.clone(), send(50))
+            .unwrap();
+
+        // Loosening waits for the timelock, tightening cancels it
+        let limit = |amount| ExecuteMsg::SetSpendingLimit {
+            denom: "token".to_string(),
+            period: day,
+            amount: Uint128::new(amount),
+        };
+        execute(deps.as_mut(), next_day.clone(), info.clone(), limit(200))
+            .unwrap();
+        let res = query::get_spending_allowance(
+            deps.as_ref(),
+            next_day.clone(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.allowances[0].limit, Uint128::new(100));
+        assert_eq!(res.pending_limits[0].amount, Some(Uint128::new(200)));
+        execute(deps.as_mut(), next_day.clone(), info.clone(), limit(100))
+            .unwrap();
+        let res = query::get_spending_allowance(
+            deps.as_ref(),
+            next_day.clone(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.pending_limits, vec![]);
+
+        let msg = ExecuteMsg::UpdateLimitExceededAction {
+            action: LimitExceededAction::Escalate,
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res =
+            execute(deps.as_mut(), next_day.clone(), info.clone(), send(60));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::SpendingLimitExceeded {
+                denom: "token".to_string()
+            }
+        );
+        let mut unlocked = mock_env();
+        unlocked.block.time =
+            unlocked.block.time.plus_seconds(LIMIT_LOOSENING_DELAY);
+        let msg = ExecuteMsg::ApplyLimitChanges {};
+        execute(deps.as_mut(), next_day.clone(), info_a.clone(), msg)
+            .unwrap();
+        let res =
+            query::get_spending_allowance(deps.as_ref(), next_day, None)
+                .unwrap();
+        assert_eq!(res.limit_exceeded_action, LimitExceededAction::Reject);
+        let msg = ExecuteMsg::ApplyLimitChanges {};
+        execute(deps.as_mut(), unlocked.clone(), info_a.clone(), msg)
+            .unwrap();
+
+        let res =
+            execute(deps.as_mut(), unlocked.clone(), info.clone(), send(110))
+                .unwrap();
+        assert_eq!(0, res.messages.len());
+        let res =
+            query::get_proposal(deps.as_ref(), unlocked.clone(), 1).unwrap();
+        assert_eq!(res.status, ProposalStatus::Open);
+
+        let msg = ExecuteMsg::ApproveProposal { proposal_id: 1 };
+        let res =
+            execute(deps.as_mut(), unlocked.clone(), info_a, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        let res = query::get_spending_allowance(
+            deps.as_ref(),
+            unlocked,
+            Some("token".to_string()),
+        )
+        .unwrap();
+        assert_eq!(res.allowances[0].remaining, Uint128::zero());
+    }
+
+    #[test]
+    fn recovery_expiry_and_retraction() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info("creator", &[]);
+        let info_a = mock_info("a", &[]);
+        let info_b = mock_info("b", &[]);
+        let info_c = mock_info("c", &[]);
+        let new_owner = Addr::unchecked("new_owner");
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![
+                member(&info_a, 1),
+                member(&info_b, 1),
+                member(&info_c, 1),
+            ],
+            approval_pool: vec![],
+            recovery_approvals_needed: 3,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: Some(100),
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::BeginSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_a.clone(), msg).unwrap();
+
+        let mut late_env = mock_env();
+        late_env.block.time = late_env.block.time.plus_seconds(100);
+        let msg = ExecuteMsg::ApproveSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        let res =
+            execute(deps.as_mut(), late_env.clone(), info_b.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::RecoveryExpired {});
+
+        // Stale process does not block a new one
+        let msg = ExecuteMsg::BeginSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), late_env.clone(), info_b.clone(), msg)
+            .unwrap();
+        let res = query::get_recovery_pool(deps.as_ref()).unwrap();
+        assert_eq!(res.recovery_initiator, Some(info_b.sender.clone()));
+        assert_eq!(res.recovery_progress, 1);
+
+        let msg = ExecuteMsg::ApproveSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), late_env.clone(), info_c.clone(), msg)
+            .unwrap();
+
+        let msg = ExecuteMsg::RetractRecoveryApproval {};
+        let res =
+            execute(deps.as_mut(), late_env.clone(), info_a.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::NotVoted {});
+        let msg = ExecuteMsg::RetractRecoveryApproval {};
+        execute(deps.as_mut(), late_env.clone(), info_c.clone(), msg)
+            .unwrap();
+        let res = query::get_recovery_pool(deps.as_ref()).unwrap();
+        assert_eq!(res.recovery_progress, 1);
+
+        let msg = ExecuteMsg::CancelRecovery {};
+        let res =
+            execute(deps.as_mut(), late_env.clone(), info_c.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let msg = ExecuteMsg::CancelRecovery {};
+        execute(deps.as_mut(), late_env, info_b.clone(), msg).unwrap();
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.potential_owner, None);
+        assert_eq!(state.recovery_initiator, None);
+        assert_eq!(ACTIVE_RECOVERY.len(&deps.storage).unwrap(), 0);
+    }
+
+    #[test]
+    fn weighted_recovery() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info("creator", &[]);
+        let info_key = mock_info("hardware_key", &[]);
+        let info_a = mock_info("a", &[]);
+        let info_b = mock_info("b", &[]);
+        let new_owner = Addr::unchecked("new_owner");
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![
+                RecoveryMember {
+                    addr: info_key.sender.clone(),
+                    weight: 2,
+                    required: true,
+                    pubkey: None,
+                    chain: None,
+                },
+                member(&info_a, 1),
+                member(&info_b, 1),
+            ],
+            approval_pool: vec![],
+            recovery_approvals_needed: 2,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: Some(0),
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::AddRecoveryMember {
+            member: Addr::unchecked("c"),
+            weight: 0,
+            required: false,
+            pubkey: None,
+            chain: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidWeight {});
+
+        // Weight is enough, but the required member did not approve
+        let msg = ExecuteMsg::BeginSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_a.clone(), msg).unwrap();
+        let msg = ExecuteMsg::ApproveSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
+        let res = query::get_recovery_pool(deps.as_ref()).unwrap();
+        assert_eq!(res.recovery_progress, 2);
+        assert_eq!(res.members[0].weight, 2);
+
+        let msg = ExecuteMsg::ApproveSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_key.clone(), msg).unwrap();
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.owner, new_owner);
+    }
+
+    #[test]
+    fn session_keys() {
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let info_key = mock_info("key", &[]);
+        let info_slave = mock_info("slave", &[]);
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info_slave.sender.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_slave.clone(), msg).unwrap();
+
+        let send = |amount| ExecuteMsg::ExecuteSameChain {
+            body_proxy: cosmwasm_std::BankMsg::Send {
+                to_address: "friend".to_string(),
+                amount: coins(amount, "token"),
+            }
+            .into(),
+        };
+        let res =
+            execute(deps.as_mut(), mock_env(), info_key.clone(), send(1));
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg = ExecuteMsg::AddSessionKey {
+            key: info_key.sender.clone(),
+            expires_in: 100,
+            spend_limit: coins(10, "token"),
+            allowed_msgs: vec![MsgKind::Bank],
+            allowed_contracts: vec![],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_key.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let msg = ExecuteMsg::AddSessionKey {
+            key: info_key.sender.clone(),
+            expires_in: 100,
+            spend_limit: coins(10, "token"),
+            allowed_msgs: vec![MsgKind::Bank],
+            allowed_contracts: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let res =
+            execute(deps.as_mut(), mock_env(), info_key.clone(), send(6))
+                .unwrap();
+        assert_eq!(1, res.messages.len());
+        let res =
+            execute(deps.as_mut(), mock_env(), info_key.clone(), send(6));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::SessionKeyLimitExceeded {
+                denom: "token".to_string()
+            }
+        );
+        let res =
+            query::get_session_key(deps.as_ref(), info_key.sender.clone())
+                .unwrap();
+        assert_eq!(res.spent, coins(6, "token"));
+
+        let msg = ExecuteMsg::ExecuteSameChain {
+            body_proxy: WasmMsg::Execute {
+                contract_addr: "dex".to_string(),
+                msg: Binary::default(),
+                funds: vec![],
+            }
+            .into(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_key.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::SessionKeyForbidden {});
+
+        // Even unrestricted keys cannot change the slave settings
+        let msg = ExecuteMsg::AddSessionKey {
+            key: Addr::unchecked("wasm_key"),
+            expires_in: 100,
+            spend_limit: vec![],
+            allowed_msgs: vec![],
+            allowed_contracts: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::ExecuteSameChain {
+            body_proxy: WasmMsg::Execute {
+                contract_addr: info_slave.sender.to_string(),
+                msg: Binary::default(),
+                funds: vec![],
+            }
+            .into(),
+        };
+        let wasm_key = mock_info("wasm_key", &[]);
+        let res = execute(deps.as_mut(), mock_env(), wasm_key, msg);
+        assert_eq!(res.unwrap_err(), ContractError::SessionKeyForbidden {});
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(100);
+        let res = execute(deps.as_mut(), later, info_key.clone(), send(1));
+        assert_eq!(res.unwrap_err(), ContractError::SessionKeyExpired {});
+
+        let msg =
+            ExecuteMsg::RevokeSessionKey { key: info_key.sender.clone() };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info_key, send(1));
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let res =
+            query::list_session_keys(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.keys.len(), 1);
+        assert_eq!(res.keys[0].key, "wasm_key");
+    }
+
+    #[test]
+    fn signed_execution() {
+        use k256::ecdsa::signature::Signer;
+        use k256::ecdsa::{Signature, SigningKey};
+
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let info_relayer = mock_info("relayer", &[]);
+        let info_slave = mock_info("slave", &[]);
+        let key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let pubkey = key.verifying_key().to_sec1_bytes().to_vec();
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: Some(pubkey.into()),
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info_slave.sender.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_slave.clone(), msg).unwrap();
+
+        let env = mock_env();
+        let signed = |nonce, chain_id: &str| {
+            let payload = to_json_binary(&SignedPayload {
+                chain_id: chain_id.to_string(),
+                host: env.contract.address.clone(),
+                nonce,
+                expires_at: env.block.time.plus_seconds(60),
+                msgs: vec![cosmwasm_std::BankMsg::Send {
+                    to_address: "friend".to_string(),
+                    amount: coins(5, "token"),
+                }
+                .into()],
+                relayer_fee: Some(Coin::new(1, "token")),
+            })
+            .unwrap();
+            let signature: Signature = key.sign(payload.as_slice());
+            ExecuteMsg::ExecuteSigned {
+                payload,
+                signature: signature.to_bytes().to_vec().into(),
+            }
+        };
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info_relayer.clone(),
+            signed(1, &env.block.chain_id),
+        )
+        .unwrap();
+        let CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) =
+            &res.messages[0].msg
+        else {
+            panic!("Expected slave execution");
+        };
+        let SlaveExecuteMsg::ExecuteBatch { msgs, .. } =
+            from_json(msg).unwrap();
+        // Payload message and relayer fee
+        assert_eq!(2, msgs.len());
+        assert!(query::get_nonce(deps.as_ref(), 1).unwrap().used);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info_relayer.clone(),
+            signed(1, &env.block.chain_id),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::NonceAlreadyUsed {});
+
+        // Skipping ahead burns every lower nonce
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info_relayer.clone(),
+            signed(5, &env.block.chain_id),
+        )
+        .unwrap();
+        assert!(query::get_nonce(deps.as_ref(), 3).unwrap().used);
+        assert!(!query::get_nonce(deps.as_ref(), 6).unwrap().used);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info_relayer.clone(),
+            signed(3, &env.block.chain_id),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::NonceAlreadyUsed {});
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info_relayer.clone(),
+            signed(2, "other-chain"),
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::SignatureDomainMismatch {}
+        );
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(60);
+        let res = execute(
+            deps.as_mut(),
+            later,
+            info_relayer.clone(),
+            signed(2, &env.block.chain_id),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::SignatureExpired {});
+
+        let ExecuteMsg::ExecuteSigned { payload, .. } =
+            signed(2, &env.block.chain_id)
+        else {
+            unreachable!()
+        };
+        let msg = ExecuteMsg::ExecuteSigned {
+            payload,
+            signature: Binary::from(vec![0u8; 64]),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_relayer, msg);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidSignature {});
+    }
+
+    #[test]
+    fn batch_execution() {
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let info_slave = mock_info("slave", &[]);
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info_slave.sender.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_slave.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::ExecuteBatch {
+            msgs: vec![],
+            mode: BatchMode::Atomic,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::EmptyBatch {});
+
+        let send: CosmosMsg = cosmwasm_std::BankMsg::Send {
+            to_address: "friend".to_string(),
+            amount: coins(5, "token"),
+        }
+        .into();
+        let msgs = vec![send.clone(), send];
+        let msg = ExecuteMsg::ExecuteBatch {
+            msgs: msgs.clone(),
+            mode: BatchMode::BestEffort,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: info_slave.sender.to_string(),
+                msg: to_json_binary(&SlaveExecuteMsg::ExecuteBatch {
+                    msgs,
+                    mode: BatchMode::BestEffort,
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        let batch = to_json_binary(&BatchResult {
+            total: 2,
+            failed: vec![BatchFailure {
+                index: 1,
+                error: "insufficient funds".to_string(),
+            }],
+        })
+        .unwrap();
+        // MsgExecuteContractResponse { data } protobuf envelope
+        let mut envelope = vec![0x0a, batch.len() as u8];
+        envelope.extend_from_slice(batch.as_slice());
+        let reply_msg = Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Ok(cosmwasm_std::SubMsgResponse {
+                events: vec![],
+                data: Some(envelope.into()),
+            }),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(res.data, Some(batch));
+    }
+
+    #[test]
+    fn transaction_history() {
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let info_slave = mock_info("slave", &[]);
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info_slave.sender.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_slave.clone(), msg).unwrap();
+
+        let send = |amount| ExecuteMsg::ExecuteSameChain {
+            body_proxy: cosmwasm_std::BankMsg::Send {
+                to_address: "friend".to_string(),
+                amount: coins(amount, "token"),
+            }
+            .into(),
+        };
+        let msg = ExecuteMsg::SetSpendingLimit {
+            denom: "token".to_string(),
+            period: 60,
+            amount: Uint128::new(100),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let info_funds = mock_info("creator", &coins(2, "fee"));
+        let res =
+            execute(deps.as_mut(), mock_env(), info_funds, send(5)).unwrap();
+        assert_eq!(res.messages[0].reply_on, cosmwasm_std::ReplyOn::Always);
+        let res = query::get_transactions(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.transactions[0].status, TxStatus::Pending);
+        assert_eq!(res.transactions[0].msgs, vec!["bank_send:friend"]);
+        assert_eq!(res.transactions[0].funds, coins(5, "token"));
+
+        // A failure is recorded and gives back spending and funds
+        let reply_msg = Reply {
+            id: ReplyKind::ReplyExecuteBatch as u64,
+            result: SubMsgResult::Err("insufficient funds".to_string()),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".to_string(),
+                amount: coins(2, "fee"),
+            })
+        );
+        let res =
+            query::get_spending_allowance(deps.as_ref(), mock_env(), None)
+                .unwrap();
+        assert_eq!(res.allowances[0].spent, Uint128::zero());
+
+        execute(deps.as_mut(), mock_env(), info.clone(), send(3)).unwrap();
+        let reply_msg = Reply {
+            id: ReplyKind::ReplyExecuteBatch as u64,
+            result: SubMsgResult::Ok(cosmwasm_std::SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+        let res = query::get_transactions(deps.as_ref(), None, None).unwrap();
+        assert_eq!(
+            res.transactions
+                .iter()
+                .map(|tx| (tx.id, tx.status.clone()))
+                .collect::<Vec<_>>(),
+            vec![(2, TxStatus::Succeeded), (1, TxStatus::Failed)]
+        );
+        assert_eq!(
+            res.transactions[1].error,
+            Some("insufficient funds".to_string())
+        );
+        let res =
+            query::get_transactions(deps.as_ref(), Some(2), Some(1)).unwrap();
+        assert_eq!(res.transactions.len(), 1);
+        assert_eq!(res.transactions[0].id, 1);
+    }
+
+    #[test]
+    fn offchain_guardian_approvals() {
+        use k256::ecdsa::signature::Signer;
+        use sha2::{Digest, Sha256};
+
+        let mut deps = mock_dependencies();
+
+        let info = mock_info("creator", &[]);
+        let info_a = mock_info("a", &[]);
+        let info_b = mock_info("b", &[]);
+        let info_c = mock_info("c", &[]);
+        let new_owner = Addr::unchecked("new_owner");
+        let key_a =
+            k256::ecdsa::SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let key_b = ed25519_zebra::SigningKey::from([9u8; 32]);
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![
+                RecoveryMember {
+                    pubkey: Some(GuardianPubkey::Secp256k1(
+                        key_a.verifying_key().to_sec1_bytes().to_vec().into(),
+                    )),
+                    ..member(&info_a, 1)
+                },
+                member(&info_b, 1),
+                member(&info_c, 1),
+            ],
+            approval_pool: vec![],
+            recovery_approvals_needed: 3,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: Some(0),
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let pubkey_b = ed25519_zebra::VerificationKey::from(&key_b);
+        let msg = ExecuteMsg::UpdateGuardianPubkey {
+            pubkey: Some(GuardianPubkey::Ed25519(
+                <[u8; 32]>::from(pubkey_b).to_vec().into(),
+            )),
+        };
+        execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
+
+        let env = mock_env();
+        let approval = |recovery_nonce| RecoveryApproval {
+            chain_id: env.block.chain_id.clone(),
+            host: env.contract.address.clone(),
+            recovery_nonce,
+            target_addr: new_owner.clone(),
+            expires_at: env.block.time.plus_seconds(3600),
+        };
+        let payload = |recovery_nonce| {
+            to_json_binary(&approval(recovery_nonce)).unwrap()
+        };
+        let sign_a = |payload: &Binary| GuardianSignature {
+            guardian: info_a.sender.clone(),
+            signature: {
+                let signature: k256::ecdsa::Signature =
+                    key_a.sign(payload.as_slice());
+                signature.to_bytes().to_vec().into()
+            },
+        };
+        let sign_b = |payload: &Binary| GuardianSignature {
+            guardian: info_b.sender.clone(),
+            signature: <[u8; 64]>::from(
+                key_b.sign(&Sha256::digest(payload.as_slice())),
+            )
+            .to_vec()
+            .into(),
+        };
+
+        let res = query::get_recovery_pool(deps.as_ref()).unwrap();
+        assert_eq!(res.recovery_nonce, 0);
+
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: payload(1),
+            signatures: vec![sign_a(&payload(1))],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_c.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::StaleRecoveryApproval {});
+
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: payload(0),
+            signatures: vec![GuardianSignature {
+                guardian: info_c.sender.clone(),
+                signature: sign_a(&payload(0)).signature,
+            }],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_c.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::MissingPubkey {});
+
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: payload(0),
+            signatures: vec![GuardianSignature {
+                guardian: info_b.sender.clone(),
+                signature: sign_a(&payload(0)).signature,
+            }],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_c.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidSignature {});
+
+        let expired = to_json_binary(&RecoveryApproval {
+            expires_at: env.block.time,
+            ..approval(0)
+        })
+        .unwrap();
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: expired.clone(),
+            signatures: vec![sign_a(&expired)],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_c.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::SignatureExpired {});
+
+        // Anyone may relay, the process starts with the first signer
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: payload(0),
+            signatures: vec![sign_a(&payload(0))],
+        };
+        execute(deps.as_mut(), mock_env(), info_c.clone(), msg).unwrap();
+        let res = query::get_recovery_pool(deps.as_ref()).unwrap();
+        assert_eq!(res.recovery_progress, 1);
+        assert_eq!(res.recovery_initiator, Some(info_a.sender.clone()));
+        assert_eq!(res.new_owner, Some(new_owner.clone()));
+
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: payload(0),
+            signatures: vec![sign_b(&payload(0)), sign_a(&payload(0))],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_c.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::AlreadyVoted {});
+
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: payload(0),
+            signatures: vec![sign_b(&payload(0))],
+        };
+        execute(deps.as_mut(), mock_env(), info_c.clone(), msg).unwrap();
+
+        // A retracted approval cannot be replayed
+        let msg = ExecuteMsg::RetractRecoveryApproval {};
+        execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: payload(0),
+            signatures: vec![sign_b(&payload(0))],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_c.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ApprovalRetracted {});
+        let res = query::get_recovery_pool(deps.as_ref()).unwrap();
+        assert_eq!(res.recovery_progress, 1);
+
+        let msg = ExecuteMsg::ApproveSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
+        let msg = ExecuteMsg::ApproveSocialRecovery {
+            target_addr: new_owner.clone(),
+        };
+        let res =
+            execute(deps.as_mut(), mock_env(), info_c.clone(), msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        let state = STATE.load(&deps.storage).unwrap();
+        assert_eq!(state.owner, new_owner);
+        assert_eq!(state.recovery_nonce, 1);
+
+        // Signatures do not outlive their process
+        let msg = ExecuteMsg::SubmitRecoveryApprovals {
+            payload: payload(0),
+            signatures: vec![sign_a(&payload(0)), sign_b(&payload(0))],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_c, msg);
+        assert_eq!(res.unwrap_err(), ContractError::StaleRecoveryApproval {});
+    }
+
+    #[test]
+    fn migration() {
+        let mut deps = mock_dependencies();
+
+        let legacy = br#"{
+            "owner": "creator",
+            "master": "master",
+            "potential_owner": null,
+            "recovery_method": null,
+            "recovery_pool": ["a", "b"],
+            "approval_pool": ["c"],
+            "recovery_approvals_needed": 1,
+            "transfer_ownership_approvals_needed": 1,
+            "chain": "foo-1"
+        }"#;
+        deps.storage.set(b"state", legacy);
+        cw_storage_plus::Deque::<(String, Addr)>::new("slaves")
+            .push_back(
+                &mut deps.storage,
+                &("foo-1".to_string(), Addr::unchecked("slave")),
+            )
+            .unwrap();
+        set_contract_version(&mut deps.storage, "crates.io:other", "0.1.0")
+            .unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::CannotMigrate {
+                previous_contract: "crates.io:other".to_string()
+            }
+        );
+
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "9.0.0")
+            .unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::CannotDowngrade {
+                previous_version: "9.0.0".to_string()
+            }
+        );
+
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "0.1.0")
+            .unwrap();
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        let res = query::get_recovery_pool(deps.as_ref()).unwrap();
+        assert_eq!(
+            res.members,
+            vec![
+                member(&mock_info("a", &[]), 1),
+                member(&mock_info("b", &[]), 1)
+            ]
+        );
+        assert_eq!(res.recovery_delay, DEFAULT_RECOVERY_DELAY);
+        let res =
+            query::get_slave(deps.as_ref(), "foo-1".to_string()).unwrap();
+        assert_eq!(res.slave, Some(Addr::unchecked("slave")));
+        let res =
+            query::list_proposals(deps.as_ref(), mock_env(), None, None)
+                .unwrap();
+        assert!(res.proposals.is_empty());
+        let version = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(version.version, CONTRACT_VERSION);
+
+        // Migrating to the same version is a no-op
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    }
+
+    #[test]
+    fn slave_registry() {
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let info_slave = mock_info("slave", &[]);
+        let info_other = mock_info("other", &[]);
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        for (chain, info) in [("foo-1", &info_slave), ("bar-1", &info_other)]
+        {
+            let msg = ExecuteMsg::RegisterSlave {
+                chain: chain.to_string(),
+                addr: info.sender.clone(),
+            };
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        }
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info_other.sender.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info_other.clone(), msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::SlaveAlreadyRegistered {}
+        );
 
-    use crate::msg::{
-        GetRecoveryPoolResponse, GetSlaveResponse, GetSlavesResponse,
-    };
+        let res = query::get_slaves(deps.as_ref(), None, None).unwrap();
+        assert_eq!(
+            res.slaves.iter().map(|s| s.chain.as_str()).collect::<Vec<_>>(),
+            vec!["bar-1", "foo-1"]
+        );
+        assert_eq!(res.slaves[1].registered_at, mock_env().block.time);
+        let res = query::get_slaves(
+            deps.as_ref(),
+            Some("bar-1".to_string()),
+            Some(1),
+        )
+        .unwrap();
+        assert_eq!(res.slaves.len(), 1);
+        assert_eq!(res.slaves[0].addr, info_slave.sender);
 
-    use super::*;
+        let msg = ExecuteMsg::ReplaceSlave {
+            chain: "foo-1".to_string(),
+            addr: info_other.sender.clone(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info_other.clone(),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        // Not instantiated by master
+        let stranger = ExecuteMsg::ReplaceSlave {
+            chain: "foo-1".to_string(),
+            addr: Addr::unchecked("stranger"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), stranger);
+        assert!(res.is_err());
+        // Created by master, but for another host
+        let foreign = ExecuteMsg::ReplaceSlave {
+            chain: "foo-1".to_string(),
+            addr: Addr::unchecked("foreign"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), foreign);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res =
+            query::get_slave(deps.as_ref(), "foo-1".to_string()).unwrap();
+        assert_eq!(res.slave, Some(info_other.sender.clone()));
 
-    pub fn get_slaves(deps: Deps) -> StdResult<GetSlavesResponse> {
-        let slaves: StdResult<HashMap<String, Addr>> =
-            SLAVES.iter(deps.storage)?.collect();
-        Ok(GetSlavesResponse { slaves: slaves? })
+        let msg = ExecuteMsg::UnregisterSlave { chain: "foo-1".to_string() };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg.clone())
+            .unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ChainNotRegistered {});
+        let msg = ExecuteMsg::ExecuteSameChain {
+            body_proxy: cosmwasm_std::BankMsg::Send {
+                to_address: "friend".to_string(),
+                amount: coins(1, "token"),
+            }
+            .into(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        assert_eq!(res.unwrap_err(), ContractError::ChainNotRegistered {});
     }
 
-    pub fn get_recovery_pool(
-        deps: Deps,
-    ) -> StdResult<GetRecoveryPoolResponse> {
-        let state = STATE.load(deps.storage)?;
-        Ok(GetRecoveryPoolResponse {
-            members: state.recovery_pool,
-            recovery_approvals_count: state.recovery_approvals_needed,
-            transfer_approvals_count: state
-                .transfer_ownership_approvals_needed,
-            recovery_progress: if state.recovery_method.is_some() {
-                ACTIVE_RECOVERY.len(deps.storage)?
-            } else {
-                0
-            },
-            recovery_method: state.recovery_method,
-            new_owner: state.potential_owner,
-        })
-    }
+    #[test]
+    fn slave_registration_auth() {
+        let mut deps = mock_deps();
 
-    pub fn get_slave(
-        deps: Deps,
-        chain: String,
-    ) -> StdResult<GetSlaveResponse> {
-        let slave = get_key(&SLAVES, deps.storage, &chain);
-        Ok(GetSlaveResponse { slave: slave.map_or(None, |s| Some(s)) })
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: mock_info("owner", &[]).sender,
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // Not instantiated by master
+        let info = mock_info("stranger", &[]);
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info.sender.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        assert!(res.is_err());
+        assert!(SLAVES.is_empty(&deps.storage));
+
+        // Master has no slave code for this chain
+        let info = mock_info("slave", &[]);
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "baz-1".to_string(),
+            addr: info.sender.clone(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ChainNotSupported {});
+
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "foo-1".to_string(),
+            addr: info.sender.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res =
+            query::get_slave(deps.as_ref(), "foo-1".to_string()).unwrap();
+        assert_eq!(res.slave, Some(info.sender));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::coins;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    #[test]
+    fn execute_on_chain() {
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let info_other = mock_info("other", &[]);
+
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::RegisterSlave {
+            chain: "bar-1".to_string(),
+            addr: info_other.sender.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), info_other.clone(), msg).unwrap();
+
+        let send: CosmosMsg = cosmwasm_std::BankMsg::Send {
+            to_address: "friend".to_string(),
+            amount: coins(5, "token"),
+        }
+        .into();
+        let msg = ExecuteMsg::ExecuteOnChain {
+            chain: "baz-1".to_string(),
+            msgs: vec![send.clone()],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ChainNotRegistered {});
+
+        let msg = ExecuteMsg::ExecuteOnChain {
+            chain: "bar-1".to_string(),
+            msgs: vec![send.clone()],
+        };
+        let res =
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "chain" && attr.value == "bar-1"));
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: info_other.sender.to_string(),
+                msg: to_json_binary(&SlaveExecuteMsg::ExecuteBatch {
+                    msgs: vec![send.clone()],
+                    mode: BatchMode::Atomic,
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
 
     #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies();
+    fn remote_slave() {
+        use crate::ibc::{
+            ibc_channel_close, ibc_channel_connect, ibc_channel_open,
+            ibc_packet_ack, ibc_packet_timeout, IBC_ORDER, IBC_VERSION,
+        };
+        use crate::msg::{SlaveAck, SlavePacket};
+        use cosmwasm_std::testing::{
+            mock_ibc_channel, mock_ibc_channel_open_try, mock_ibc_packet_ack,
+            mock_ibc_packet_timeout,
+        };
+        use cosmwasm_std::{
+            IbcAcknowledgement, IbcChannelCloseMsg, IbcChannelConnectMsg,
+            IbcChannelOpenMsg,
+        };
 
-        let info = mock_info("creator", &coins(1000, "earth"));
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
             recovery_pool: vec![],
             approval_pool: vec![],
             recovery_approvals_needed: 0,
             transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
             owner: info.sender.clone(),
             chain: "foo-1".to_string(),
         };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        // Nobody is expected yet
+        let open =
+            mock_ibc_channel_open_try("channel-1", IBC_ORDER, IBC_VERSION);
+        let res = ibc_channel_open(deps.as_mut(), mock_env(), open);
+        assert_eq!(res.unwrap_err(), ContractError::UnexpectedSlave {});
+
+        let msg = ExecuteMsg::AllowRemoteSlave {
+            chain: "baz-1".to_string(),
+            port_id: "wasm.remote".to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ChainNotSupported {});
+        let msg = ExecuteMsg::AllowRemoteSlave {
+            chain: "bar-1".to_string(),
+            port_id: "wasm.remote".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let mut channel =
+            mock_ibc_channel("channel-1", IBC_ORDER, IBC_VERSION);
+        channel.counterparty_endpoint.port_id = "wasm.remote".to_string();
+        let open = IbcChannelOpenMsg::new_try(channel.clone(), "ics20-1");
+        let res = ibc_channel_open(deps.as_mut(), mock_env(), open);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::InvalidIbcVersion {
+                version: "ics20-1".to_string()
+            }
+        );
+        // Only the connection registered in master reaches the chain
+        let mut other = channel.clone();
+        other.connection_id = "connection-7".to_string();
+        let open = IbcChannelOpenMsg::new_try(other, IBC_VERSION);
+        let res = ibc_channel_open(deps.as_mut(), mock_env(), open);
+        assert_eq!(res.unwrap_err(), ContractError::UnexpectedConnection {});
+        let open = IbcChannelOpenMsg::new_try(channel.clone(), IBC_VERSION);
+        ibc_channel_open(deps.as_mut(), mock_env(), open).unwrap();
+        let connect = IbcChannelConnectMsg::new_confirm(channel.clone());
+        ibc_channel_connect(deps.as_mut(), mock_env(), connect).unwrap();
+        let slave = SLAVES.load(&deps.storage, "bar-1").unwrap();
+        assert_eq!(slave.addr, "remote");
+        assert_eq!(
+            slave.kind,
+            SlaveKind::Ibc { channel_id: "channel-1".to_string() }
+        );
+        let msg = ExecuteMsg::ReplaceSlave {
+            chain: "bar-1".to_string(),
+            addr: Addr::unchecked("slave"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::NotLocalSlave {});
+
+        let send: CosmosMsg = cosmwasm_std::BankMsg::Send {
+            to_address: "friend".to_string(),
+            amount: coins(5, "token"),
+        }
+        .into();
+        let msg = ExecuteMsg::ExecuteOnChain {
+            chain: "bar-1".to_string(),
+            msgs: vec![send.clone()],
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &coins(5, "token")),
+            msg.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::FundsNotSupported {});
+        let res =
+            execute(deps.as_mut(), mock_env(), info.clone(), msg.clone())
+                .unwrap();
+        let packet = SlavePacket::ExecuteBatch {
+            tx_id: 1,
+            msgs: vec![send],
+            mode: BatchMode::Atomic,
+        };
+        let CosmosMsg::Ibc(IbcMsg::SendPacket { channel_id, data, .. }) =
+            &res.messages[0].msg
+        else {
+            panic!("Packet expected");
+        };
+        assert_eq!(channel_id, "channel-1");
+        assert_eq!(from_json::<SlavePacket>(data).unwrap(), packet);
+
+        let ack = SlaveAck::Result(BatchResult { total: 1, failed: vec![] });
+        let ack = IbcAcknowledgement::new(to_json_binary(&ack).unwrap());
+        let ack_msg = mock_ibc_packet_ack("channel-1", &packet, ack).unwrap();
+        ibc_packet_ack(deps.as_mut(), mock_env(), ack_msg).unwrap();
+        let tx = TRANSACTIONS.load(&deps.storage, 1).unwrap();
+        assert_eq!(tx.status, TxStatus::Succeeded);
+
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let SlavePacket::ExecuteBatch { msgs, mode, .. } = packet else {
+            panic!("Not a batch packet");
+        };
+        let packet = SlavePacket::ExecuteBatch { tx_id: 2, msgs, mode };
+        let timeout = mock_ibc_packet_timeout("channel-1", &packet).unwrap();
+        ibc_packet_timeout(deps.as_mut(), mock_env(), timeout).unwrap();
+        let tx = TRANSACTIONS.load(&deps.storage, 2).unwrap();
+        assert_eq!(tx.status, TxStatus::Failed);
+        assert_eq!(tx.error, Some("Packet timed out.".to_string()));
+
+        let close = IbcChannelCloseMsg::new_confirm(channel);
+        ibc_channel_close(deps.as_mut(), mock_env(), close).unwrap();
+        assert!(!SLAVES.has(&deps.storage, "bar-1"));
+    }
+
+    /// Answers the interchain account query of the controller module,
+    /// everything else goes to [`mock_deps`].
+    struct IcaQuerier(MockQuerier);
+
+    impl Querier for IcaQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_json(bin_request) {
+                Ok(QueryRequest::<Empty>::Stargate { path, .. })
+                    if path == ica::ICA_ADDRESS_QUERY =>
+                {
+                    let res = ica::InterchainAccountResponse {
+                        address: "hub1ica".to_string(),
+                    };
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_json_binary(&res).unwrap(),
+                    ))
+                }
+                _ => self.0.raw_query(bin_request),
+            }
+        }
     }
 
     #[test]
-    fn recovery() {
-        let mut deps = mock_dependencies();
+    fn interchain_account() {
+        use crate::ica::{
+            ibc_source_callback, CallbackAddress, IbcAckCallbackMsg,
+            IbcSourceCallbackMsg, IbcTimeoutCallbackMsg, IcaMemo,
+            IcaPacketData, REGISTER_ICA_TYPE_URL, SEND_TX_TYPE_URL,
+        };
+        use cosmwasm_std::{
+            to_json_string, IbcAcknowledgement, IbcEndpoint, IbcPacket,
+            IbcTimeout, StdAck,
+        };
 
-        let info = mock_info("creator", &coins(2, "token"));
-        let creator = info.sender.clone();
-        let info_a = mock_info("a", &coins(2, "token"));
-        let info_b = mock_info("b", &coins(2, "token"));
-        let info_new_owner = mock_info("new_owner", &coins(2, "token"));
-        let new_owner = info_new_owner.sender.clone();
+        let base = mock_deps();
+        let mut deps = OwnedDeps {
+            storage: base.storage,
+            api: base.api,
+            querier: IcaQuerier(base.querier),
+            custom_query_type: std::marker::PhantomData,
+        };
 
+        let info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
-            recovery_pool: vec![info_a.sender.clone(), info_b.sender.clone()],
+            recovery_pool: vec![],
             approval_pool: vec![],
-            recovery_approvals_needed: 2,
+            recovery_approvals_needed: 0,
             transfer_ownership_approvals_needed: 0,
-            owner: creator.clone(),
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
             chain: "foo-1".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-        let msg = ExecuteMsg::BeginSocialRecovery {
-            target_addr: new_owner.clone(),
+        let register = |chain: &str| ExecuteMsg::RegisterInterchainAccount {
+            chain: chain.to_string(),
         };
-        execute(deps.as_mut(), mock_env(), info_a.clone(), msg).unwrap();
-        let state = STATE.load(&deps.storage).unwrap();
-        assert_eq!(state.potential_owner, Some(new_owner.clone()));
-        assert_eq!(state.owner, creator);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            register("bar-1"),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::ChainNotSupported {});
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            register("hub-4"),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let confirm = ExecuteMsg::ConfirmInterchainAccount {
+            chain: "hub-4".to_string(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            confirm.clone(),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::UnexpectedSlave {});
 
-        let msg = ExecuteMsg::ApproveSocialRecovery {};
-        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
-        assert_eq!(res.unwrap_err(), ContractError::AlreadyVoted {});
-        let state = STATE.load(&deps.storage).unwrap();
-        assert_eq!(state.potential_owner, Some(new_owner.clone()));
-        assert_eq!(state.owner, creator);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            register("hub-4"),
+        )
+        .unwrap();
+        // MsgRegisterInterchainAccount { owner, connection_id }
+        let mut value = vec![0x0a, 15];
+        value.extend_from_slice(MOCK_CONTRACT_ADDR.as_bytes());
+        value.extend_from_slice(&[0x12, 12]);
+        value.extend_from_slice(b"connection-3");
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Stargate {
+                type_url: REGISTER_ICA_TYPE_URL.to_string(),
+                value: value.into(),
+            }
+        );
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            confirm,
+        )
+        .unwrap();
+        let slave = SLAVES.load(&deps.storage, "hub-4").unwrap();
+        assert_eq!(slave.addr, "hub1ica");
+        assert_eq!(
+            slave.kind,
+            SlaveKind::Ica { connection_id: "connection-3".to_string() }
+        );
 
-        let msg = ExecuteMsg::ApproveSocialRecovery {};
-        execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
-        let state = STATE.load(&deps.storage).unwrap();
-        assert_eq!(state.potential_owner, None);
-        assert_eq!(state.owner, new_owner.clone());
+        let on_hub = |msg: CosmosMsg| ExecuteMsg::ExecuteOnChain {
+            chain: "hub-4".to_string(),
+            msgs: vec![msg],
+        };
+        let clear_admin = CosmosMsg::Wasm(WasmMsg::ClearAdmin {
+            contract_addr: "contract".to_string(),
+        });
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            on_hub(clear_admin),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::UnsupportedIcaMessage {});
+
+        let send: CosmosMsg = cosmwasm_std::BankMsg::Send {
+            to_address: "friend".to_string(),
+            amount: coins(5, "token"),
+        }
+        .into();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            on_hub(send.clone()),
+        )
+        .unwrap();
+        let CosmosMsg::Stargate { type_url, value } = &res.messages[0].msg
+        else {
+            panic!("Expected MsgSendTx");
+        };
+        assert_eq!(type_url, SEND_TX_TYPE_URL);
+        // CosmosTx with a single MsgSend from the interchain account
+        let tx = Binary::from_base64(
+            "Cj0KHC9jb3Ntb3MuYmFuay52MWJldGExLk1zZ1NlbmQSHQoHaHViMWljYRIGZn\
+             JpZW5kGgoKBXRva2VuEgE1",
+        )
+        .unwrap();
+        assert!(value.windows(tx.len()).any(|w| w == tx.as_slice()));
+        let memo = to_json_string(&IcaMemo {
+            src_callback: CallbackAddress {
+                address: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            },
+            tx_id: 1,
+        })
+        .unwrap();
+        assert!(value.windows(memo.len()).any(|w| w == memo.as_bytes()));
+
+        let packet = |port: &str, tx_id| {
+            let memo = IcaMemo {
+                src_callback: CallbackAddress {
+                    address: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                },
+                tx_id,
+            };
+            let data = IcaPacketData {
+                kind: "TYPE_EXECUTE_TX".to_string(),
+                data: tx.clone(),
+                memo: to_json_string(&memo).unwrap(),
+            };
+            IbcPacket::new(
+                to_json_binary(&data).unwrap(),
+                IbcEndpoint {
+                    port_id: port.to_string(),
+                    channel_id: "channel-7".to_string(),
+                },
+                IbcEndpoint {
+                    port_id: "icahost".to_string(),
+                    channel_id: "channel-0".to_string(),
+                },
+                tx_id,
+                IbcTimeout::with_timestamp(mock_env().block.time),
+            )
+        };
+        let port = format!("icacontroller-{MOCK_CONTRACT_ADDR}");
+        let ack = |packet, ack: StdAck| {
+            IbcSourceCallbackMsg::Acknowledgement(IbcAckCallbackMsg {
+                acknowledgement: IbcAcknowledgement::new(
+                    to_json_binary(&ack).unwrap(),
+                ),
+                original_packet: packet,
+                relayer: Addr::unchecked("relayer"),
+            })
+        };
+
+        // Only packets of our own controller port are accepted
+        let msg = ack(packet("icacontroller-other", 1), StdAck::error("x"));
+        let res = ibc_source_callback(deps.as_mut(), mock_env(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let msg = ack(packet(&port, 1), StdAck::error("out of gas"));
+        let res =
+            ibc_source_callback(deps.as_mut(), mock_env(), msg).unwrap();
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "error" && attr.value == "out of gas"));
+        let tx = TRANSACTIONS.load(&deps.storage, 1).unwrap();
+        assert_eq!(tx.status, TxStatus::Failed);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            on_hub(send.clone()),
+        )
+        .unwrap();
+        let msg = ack(packet(&port, 2), StdAck::success(b"\x01"));
+        ibc_source_callback(deps.as_mut(), mock_env(), msg).unwrap();
+        let tx = TRANSACTIONS.load(&deps.storage, 2).unwrap();
+        assert_eq!(tx.status, TxStatus::Succeeded);
+
+        // A timeout closes the channel, registering again reopens it
+        execute(deps.as_mut(), mock_env(), info.clone(), on_hub(send))
+            .unwrap();
+        let msg = IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg {
+            packet: packet(&port, 3),
+            relayer: Addr::unchecked("relayer"),
+        });
+        ibc_source_callback(deps.as_mut(), mock_env(), msg).unwrap();
+        let tx = TRANSACTIONS.load(&deps.storage, 3).unwrap();
+        assert_eq!(tx.status, TxStatus::Failed);
+        assert_eq!(tx.error, Some("Packet timed out.".to_string()));
+        execute(deps.as_mut(), mock_env(), info, register("hub-4")).unwrap();
     }
 
     #[test]
-    fn transfer_ownership() {
-        let mut deps = mock_dependencies();
+    fn remote_guardian() {
+        use crate::ibc::{
+            ibc_channel_connect, ibc_packet_receive, IBC_ORDER, IBC_VERSION,
+        };
+        use crate::msg::HostPacket;
+        use cosmwasm_std::testing::{mock_ibc_channel, mock_ibc_packet_recv};
+        use cosmwasm_std::{Attribute, IbcChannelConnectMsg, StdAck};
 
-        let info = mock_info("creator", &coins(2, "token"));
-        let creator = info.sender.clone();
-        let info_a = mock_info("a", &coins(2, "token"));
-        let info_b = mock_info("b", &coins(2, "token"));
-        let info_new_owner = mock_info("new_owner", &coins(2, "token"));
-        let new_owner = info_new_owner.sender.clone();
+        let mut deps = mock_deps();
 
+        let info = mock_info("creator", &[]);
+        let info_a = mock_info("a", &[]);
+        let new_owner = Addr::unchecked("new_owner");
         let msg = InstantiateMsg {
-            recovery_pool: vec![info_a.sender.clone(), info_b.sender.clone()],
+            recovery_pool: vec![
+                member(&info_a, 1),
+                RecoveryMember {
+                    chain: Some("bar-1".to_string()),
+                    ..member(&mock_info("guardian", &[]), 1)
+                },
+            ],
             approval_pool: vec![],
             recovery_approvals_needed: 2,
-            transfer_ownership_approvals_needed: 2,
-            owner: creator.clone(),
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
             chain: "foo-1".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
 
-        let msg = ExecuteMsg::BeginTransferOwnership {
-            target_addr: creator.clone(),
+        let msg = ExecuteMsg::AllowRemoteSlave {
+            chain: "bar-1".to_string(),
+            port_id: "wasm.remote".to_string(),
         };
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
-        assert_eq!(res.unwrap_err(), ContractError::SelfRecovery {});
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let mut channel =
+            mock_ibc_channel("channel-1", IBC_ORDER, IBC_VERSION);
+        channel.counterparty_endpoint.port_id = "wasm.remote".to_string();
+        let connect = IbcChannelConnectMsg::new_confirm(channel);
+        ibc_channel_connect(deps.as_mut(), mock_env(), connect).unwrap();
 
-        let msg = ExecuteMsg::BeginTransferOwnership {
+        let msg = ExecuteMsg::BeginSocialRecovery {
             target_addr: new_owner.clone(),
         };
-        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
-        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), info_a, msg).unwrap();
 
-        let msg = ExecuteMsg::BeginTransferOwnership {
+        // Same address on the host chain is somebody else
+        let msg = ExecuteMsg::ApproveSocialRecovery {
             target_addr: new_owner.clone(),
         };
-        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        let state = STATE.load(&deps.storage).unwrap();
-        assert_eq!(state.potential_owner, Some(new_owner.clone()));
-        assert_eq!(state.owner, creator);
-
-        let msg = ExecuteMsg::ApproveTransferOwnership {};
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("guardian", &[]),
+            msg,
+        );
         assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
-        let state = STATE.load(&deps.storage).unwrap();
-        assert_eq!(state.potential_owner, Some(new_owner.clone()));
-        assert_eq!(state.owner, creator);
 
-        let msg = ExecuteMsg::ApproveTransferOwnership {};
-        execute(deps.as_mut(), mock_env(), info_a.clone(), msg).unwrap();
-        let state = STATE.load(&deps.storage).unwrap();
-        assert_eq!(state.potential_owner, Some(new_owner.clone()));
-        assert_eq!(state.owner, creator);
+        let approval = |guardian: &str| HostPacket::ApproveRecovery {
+            guardian: guardian.to_string(),
+            target: new_owner.to_string(),
+        };
+        let recv =
+            mock_ibc_packet_recv("channel-2", &approval("guardian")).unwrap();
+        let res =
+            ibc_packet_receive(deps.as_mut(), mock_env(), recv).unwrap();
+        let ack: StdAck = from_json(res.acknowledgement).unwrap();
+        assert_eq!(ack, StdAck::error("Unauthorized"));
+        let recv =
+            mock_ibc_packet_recv("channel-1", &approval("stranger")).unwrap();
+        let res =
+            ibc_packet_receive(deps.as_mut(), mock_env(), recv).unwrap();
+        let ack: StdAck = from_json(res.acknowledgement).unwrap();
+        assert_eq!(ack, StdAck::error("Unauthorized"));
+        assert_eq!(
+            STATE.load(&deps.storage).unwrap().recovery_unlocks_at,
+            None
+        );
 
-        let msg = ExecuteMsg::ApproveTransferOwnership {};
-        let res = execute(deps.as_mut(), mock_env(), info_a.clone(), msg);
-        assert_eq!(res.unwrap_err(), ContractError::AlreadyVoted {});
+        let recv =
+            mock_ibc_packet_recv("channel-1", &approval("guardian")).unwrap();
+        let res =
+            ibc_packet_receive(deps.as_mut(), mock_env(), recv).unwrap();
+        let ack: StdAck = from_json(res.acknowledgement).unwrap();
+        assert!(ack.is_success());
+        assert!(res.attributes.contains(&Attribute::new("chain", "bar-1")));
         let state = STATE.load(&deps.storage).unwrap();
+        assert!(state.recovery_unlocks_at.is_some());
         assert_eq!(state.potential_owner, Some(new_owner.clone()));
-        assert_eq!(state.owner, creator);
 
-        let msg = ExecuteMsg::ApproveTransferOwnership {};
-        execute(deps.as_mut(), mock_env(), info_b.clone(), msg).unwrap();
+        let recv =
+            mock_ibc_packet_recv("channel-1", &approval("guardian")).unwrap();
+        let res =
+            ibc_packet_receive(deps.as_mut(), mock_env(), recv).unwrap();
+        let ack: StdAck = from_json(res.acknowledgement).unwrap();
+        assert_eq!(ack, StdAck::error("You already approved this process."));
+
+        // Removed by their voter identity
+        let msg = ExecuteMsg::RemoveRecoveryMember {
+            member: Addr::unchecked("bar-1/guardian"),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
         let state = STATE.load(&deps.storage).unwrap();
-        assert_eq!(state.potential_owner, None);
-        assert_eq!(state.owner, new_owner.clone());
+        assert_eq!(state.recovery_pool.len(), 1);
+    }
+
+    #[test]
+    fn config_sync() {
+        use crate::ibc::{
+            ibc_channel_connect, ibc_packet_ack, ibc_packet_timeout,
+            IBC_ORDER, IBC_VERSION,
+        };
+        use crate::msg::{SlaveConfig, SlavePacket, SlaveSyncStatusResponse};
+        use cosmwasm_std::testing::{
+            mock_ibc_channel, mock_ibc_packet_ack, mock_ibc_packet_timeout,
+        };
+        use cosmwasm_std::{
+            IbcAcknowledgement, IbcChannelConnectMsg, IbcMsg, StdAck,
+        };
+
+        let mut deps = mock_deps();
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::AllowRemoteSlave {
+            chain: "bar-1".to_string(),
+            port_id: "wasm.remote".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let mut channel =
+            mock_ibc_channel("channel-1", IBC_ORDER, IBC_VERSION);
+        channel.counterparty_endpoint.port_id = "wasm.remote".to_string();
+        let connect = IbcChannelConnectMsg::new_confirm(channel);
+        ibc_channel_connect(deps.as_mut(), mock_env(), connect).unwrap();
+
+        let status = |deps: Deps| {
+            query::get_slave_sync_status(deps, "bar-1".to_string()).unwrap()
+        };
+        let sent = |msgs: &[SubMsg]| match &msgs[0].msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket {
+                channel_id, data, ..
+            }) => {
+                assert_eq!(channel_id, "channel-1");
+                from_json::<SlavePacket>(data).unwrap()
+            }
+            msg => panic!("Unexpected message: {msg:?}"),
+        };
+        assert_eq!(
+            status(deps.as_ref()),
+            SlaveSyncStatusResponse {
+                version: 0,
+                synced_version: None,
+                in_sync: false
+            }
+        );
+
+        let msg = ExecuteMsg::SyncSlave { chain: "baz-1".to_string() };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert_eq!(res.unwrap_err(), ContractError::ChainNotRegistered {});
+
+        let msg = ExecuteMsg::AddRecoveryMember {
+            member: Addr::unchecked("guardian"),
+            weight: 1,
+            required: false,
+            pubkey: None,
+            chain: Some("bar-1".to_string()),
+        };
+        let res =
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let packet = sent(&res.messages);
+        assert_eq!(
+            res.messages[1].msg,
+            WasmMsg::Execute {
+                contract_addr: "creator".to_string(),
+                msg: to_json_binary(&MasterMsg::UpdateGuardians {
+                    guardians: vec![Addr::unchecked("bar-1/guardian")],
+                })
+                .unwrap(),
+                funds: vec![],
+            }
+            .into()
+        );
+        assert_eq!(
+            packet,
+            SlavePacket::SyncConfig {
+                version: 1,
+                config: SlaveConfig {
+                    owner: "creator".to_string(),
+                    guardians: vec!["guardian".to_string()],
+                },
+            }
+        );
+        let msg = ExecuteMsg::RemoveRecoveryMember {
+            member: Addr::unchecked("bar-1/guardian"),
+        };
+        let res =
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let latest = sent(&res.messages);
+
+        // Superseded configs are not retried
+        let timeout = mock_ibc_packet_timeout("channel-1", &packet).unwrap();
+        let res = ibc_packet_timeout(deps.as_mut(), mock_env(), timeout);
+        assert_eq!(res.unwrap().messages.len(), 0);
+        let timeout = mock_ibc_packet_timeout("channel-1", &latest).unwrap();
+        let res =
+            ibc_packet_timeout(deps.as_mut(), mock_env(), timeout).unwrap();
+        assert_eq!(sent(&res.messages), latest);
+
+        let ack = IbcAcknowledgement::new(StdAck::success(b""));
+        let msg = mock_ibc_packet_ack("channel-1", &latest, ack).unwrap();
+        ibc_packet_ack(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(
+            status(deps.as_ref()),
+            SlaveSyncStatusResponse {
+                version: 2,
+                synced_version: Some(2),
+                in_sync: true
+            }
+        );
+        // Late acknowledgement of an older config
+        let ack = IbcAcknowledgement::new(StdAck::success(b""));
+        let msg = mock_ibc_packet_ack("channel-1", &packet, ack).unwrap();
+        ibc_packet_ack(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(status(deps.as_ref()).synced_version, Some(2));
+
+        let msg = ExecuteMsg::SyncSlave { chain: "bar-1".to_string() };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(sent(&res.messages), latest);
     }
 }
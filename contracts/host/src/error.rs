@@ -15,12 +15,69 @@ pub enum ContractError {
     #[error("Member already added")]
     MemberAlreadyAdded {},
 
-    #[error("Nonce expired")]
+    #[error("Member weight must be positive")]
+    InvalidWeight {},
+
+    #[error("Nonce already used")]
     NonceAlreadyUsed {},
 
+    #[error("Owner public key is not set.")]
+    MissingPubkey {},
+
+    #[error("Invalid secp256k1 public key.")]
+    InvalidPubkey {},
+
+    #[error("Invalid signature.")]
+    InvalidSignature {},
+
+    #[error("Payload was signed for another chain or host.")]
+    SignatureDomainMismatch {},
+
+    #[error("Signed payload expired.")]
+    SignatureExpired {},
+
+    #[error("Approval was signed for another recovery process.")]
+    StaleRecoveryApproval {},
+
+    #[error("Guardian retracted its approval of this process.")]
+    ApprovalRetracted {},
+
+    #[error("At least one signature is required.")]
+    NoSignatures {},
+
     #[error("No slave controller present on requested chain.")]
     ChainNotRegistered {},
 
+    #[error("Only remote slaves mirror the wallet config.")]
+    NotRemoteSlave {},
+
+    #[error("Only same-chain slaves can be replaced.")]
+    NotLocalSlave {},
+
+    #[error("Slave controller already registered for this chain.")]
+    SlaveAlreadyRegistered {},
+
+    #[error("Chain is not supported by master.")]
+    ChainNotSupported {},
+
+    #[error("Funds cannot be attached for remote slaves.")]
+    FundsNotSupported {},
+
+    #[error("Only unordered channels are supported.")]
+    InvalidIbcOrder {},
+
+    #[error("Invalid IBC channel version: {version}")]
+    InvalidIbcVersion { version: String },
+
+    #[error("No remote slave expected on this port.")]
+    UnexpectedSlave {},
+
+    #[error("Message cannot be executed by an interchain account.")]
+    UnsupportedIcaMessage {},
+
+    #[error("Channel is not on the connection registered for the chain.")]
+    UnexpectedConnection {},
+
     #[error("Cannot transfer ownership to yourself.")]
     SelfRecovery {},
 
@@ -33,6 +90,66 @@ pub enum ContractError {
     #[error("The requested process was not initiated yet.")]
     NotInProgress {},
 
+    #[error("You have not approved this process.")]
+    NotVoted {},
+
+    #[error("The requested process expired.")]
+    RecoveryExpired {},
+
+    #[error("Recovery delay has not passed yet.")]
+    RecoveryLocked {},
+
+    #[error("Recovery delay cannot exceed {max} seconds.")]
+    RecoveryDelayTooLong { max: u64 },
+
+    #[error("Approval pool is empty, nobody can approve the proposal.")]
+    EmptyApprovalPool {},
+
+    #[error("Proposal approvals needed must be between 1 and {max}.")]
+    InvalidApprovalsNeeded { max: u32 },
+
+    #[error("Proposal must contain at least one message.")]
+    EmptyProposal {},
+
+    #[error("Batch must contain at least one message.")]
+    EmptyBatch {},
+
+    #[error("Unknown reply ID: {0}")]
+    UnknownReplyID(u64),
+
+    #[error("Proposal is not open for voting.")]
+    ProposalNotOpen {},
+
+    #[error("Proposal expired.")]
+    ProposalExpired {},
+
+    #[error("Spending limit exceeded for {denom}.")]
+    SpendingLimitExceeded { denom: String },
+
+    #[error("Cannot check spending limits for this message type.")]
+    UncheckableMessage {},
+
+    #[error("Spending limit period must be positive.")]
+    InvalidPeriod {},
+
+    #[error("Session key expired.")]
+    SessionKeyExpired {},
+
+    #[error("Session key is not allowed to execute this message.")]
+    SessionKeyForbidden {},
+
+    #[error("Session key spend limit exceeded for {denom}.")]
+    SessionKeyLimitExceeded { denom: String },
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from newer version {previous_version}")]
+    CannotDowngrade { previous_version: String },
+
+    #[error("Invalid contract version: {0}")]
+    InvalidVersion(String),
+
     #[error("{0}")]
     InvariantMismatch(String),
 }
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{
+    Addr, BankMsg, Coin, CosmosMsg, DistributionMsg, Env, GovMsg, IbcMsg,
+    StakingMsg, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+
+use crate::spending::outgoing_funds;
+use crate::state::{
+    Transaction, TxStatus, PENDING_TXS, TRANSACTIONS, TX_COUNT,
+};
+
+/// Short human readable description of `msg`, e.g. `bank_send:addr`.
+pub fn summarize(msg: &CosmosMsg) -> String {
+    match msg {
+        CosmosMsg::Bank(BankMsg::Send { to_address, .. }) => {
+            format!("bank_send:{to_address}")
+        }
+        CosmosMsg::Bank(BankMsg::Burn { .. }) => "bank_burn".to_string(),
+        CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) => {
+            format!("wasm_execute:{contract_addr}")
+        }
+        CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, .. }) => {
+            format!("wasm_instantiate:{code_id}")
+        }
+        CosmosMsg::Wasm(WasmMsg::Migrate { contract_addr, .. }) => {
+            format!("wasm_migrate:{contract_addr}")
+        }
+        CosmosMsg::Wasm(WasmMsg::UpdateAdmin { contract_addr, .. }) => {
+            format!("wasm_update_admin:{contract_addr}")
+        }
+        CosmosMsg::Wasm(WasmMsg::ClearAdmin { contract_addr }) => {
+            format!("wasm_clear_admin:{contract_addr}")
+        }
+        CosmosMsg::Staking(StakingMsg::Delegate { validator, .. }) => {
+            format!("staking_delegate:{validator}")
+        }
+        CosmosMsg::Staking(StakingMsg::Undelegate { validator, .. }) => {
+            format!("staking_undelegate:{validator}")
+        }
+        CosmosMsg::Staking(StakingMsg::Redelegate {
+            dst_validator, ..
+        }) => {
+            format!("staking_redelegate:{dst_validator}")
+        }
+        CosmosMsg::Distribution(
+            DistributionMsg::WithdrawDelegatorReward { validator },
+        ) => format!("distribution_withdraw_reward:{validator}"),
+        CosmosMsg::Distribution(_) => "distribution".to_string(),
+        CosmosMsg::Ibc(IbcMsg::Transfer { channel_id, .. }) => {
+            format!("ibc_transfer:{channel_id}")
+        }
+        CosmosMsg::Gov(GovMsg::Vote { proposal_id, .. }) => {
+            format!("gov_vote:{proposal_id}")
+        }
+        CosmosMsg::Stargate { type_url, .. } => {
+            format!("stargate:{type_url}")
+        }
+        _ => "other".to_string(),
+    }
+}
+
+fn total_funds(msgs: &[CosmosMsg]) -> Vec<Coin> {
+    let mut totals = BTreeMap::<String, Uint128>::new();
+    for coin in msgs.iter().filter_map(outgoing_funds).flatten() {
+        *totals.entry(coin.denom).or_default() += coin.amount;
+    }
+    totals.into_iter().map(|(denom, amount)| Coin { denom, amount }).collect()
+}
+
+/// Record a pending transaction, to be completed by [`finish`] from the
+/// slave execution reply.
+pub fn begin(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    msgs: &[CosmosMsg],
+) -> StdResult<u64> {
+    let id = record(storage, env, sender, msgs)?;
+    // Slave may call back into the host, so replies arrive in LIFO order
+    PENDING_TXS.push_back(storage, &id)?;
+    Ok(id)
+}
+
+/// Record a pending transaction completed later by id, e.g. from a packet
+/// acknowledgement.
+pub fn record(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    msgs: &[CosmosMsg],
+) -> StdResult<u64> {
+    let id = TX_COUNT.load(storage)? + 1;
+    TX_COUNT.save(storage, &id)?;
+    let tx = Transaction {
+        id,
+        sender: sender.clone(),
+        height: env.block.height,
+        time: env.block.time,
+        msgs: msgs.iter().map(summarize).collect(),
+        funds: total_funds(msgs),
+        status: TxStatus::Pending,
+        failed_msgs: vec![],
+        error: None,
+    };
+    TRANSACTIONS.save(storage, id, &tx)?;
+    Ok(id)
+}
+
+pub fn finish(
+    storage: &mut dyn Storage,
+    status: TxStatus,
+    failed_msgs: Vec<u32>,
+    error: Option<String>,
+) -> StdResult<Transaction> {
+    let id = PENDING_TXS
+        .pop_back(storage)?
+        .ok_or_else(|| StdError::generic_err("No pending transaction."))?;
+    complete(storage, id, status, failed_msgs, error)
+}
+
+pub fn complete(
+    storage: &mut dyn Storage,
+    id: u64,
+    status: TxStatus,
+    failed_msgs: Vec<u32>,
+    error: Option<String>,
+) -> StdResult<Transaction> {
+    TRANSACTIONS.update(storage, id, |tx| {
+        let tx = tx.ok_or_else(|| StdError::not_found("Transaction"))?;
+        Ok(Transaction { status, failed_msgs, error, ..tx })
+    })
+}
@@ -0,0 +1,448 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, CosmosMsg, Deps, DepsMut, Env,
+    Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannel,
+    IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg,
+    IbcChannelOpenResponse, IbcMsg, IbcOrder, IbcPacket, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout,
+    MessageInfo, Never, Order, StdAck, StdError, StdResult, Storage,
+};
+
+use crate::error::ContractError;
+use crate::history;
+use crate::msg::{
+    BatchMode, ChainInfo, ChainResponse, ChainSlaveKind, ExecuteMsg,
+    HostPacket, MasterQueryMsg, SlaveAck, SlaveConfig, SlavePacket,
+};
+use crate::state::{
+    remote_voter, SlaveInfo, SlaveKind, Transaction, TxStatus,
+    CONFIG_VERSION, EXPECTED_SLAVES, SLAVES, STATE, SYNCED_CONFIGS,
+};
+
+pub const IBC_VERSION: &str = "ward-1";
+pub const IBC_ORDER: IbcOrder = IbcOrder::Unordered;
+/// Packets not relayed within an hour fail.
+pub const PACKET_LIFETIME: u64 = 60 * 60;
+
+fn validate_channel(
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> Result<(), ContractError> {
+    if channel.order != IBC_ORDER {
+        return Err(ContractError::InvalidIbcOrder {});
+    }
+    for version in
+        std::iter::once(channel.version.as_str()).chain(counterparty_version)
+    {
+        if version != IBC_VERSION {
+            return Err(ContractError::InvalidIbcVersion {
+                version: version.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Chain registered in the master with `kind` slaves over a connection.
+pub fn remote_chain(
+    deps: Deps,
+    master: &Addr,
+    chain_id: &str,
+    kind: ChainSlaveKind,
+) -> Result<ChainInfo, ContractError> {
+    let res: ChainResponse = deps.querier.query_wasm_smart(
+        master,
+        &MasterQueryMsg::GetChain { chain_id: chain_id.to_string() },
+    )?;
+    res.chain
+        .filter(|chain| {
+            chain.slave_kind == kind && chain.connection_id.is_some()
+        })
+        .ok_or(ContractError::ChainNotSupported {})
+}
+
+/// Chain of the slave the owner allowed on the other end of `channel`,
+/// which must use the connection the master registered for it.
+fn expected_chain(
+    deps: Deps,
+    channel: &IbcChannel,
+) -> Result<String, ContractError> {
+    let chain = EXPECTED_SLAVES
+        .may_load(deps.storage, &channel.counterparty_endpoint.port_id)?
+        .ok_or(ContractError::UnexpectedSlave {})?;
+    if SLAVES.has(deps.storage, &chain) {
+        return Err(ContractError::SlaveAlreadyRegistered {});
+    }
+    let master = STATE.load(deps.storage)?.master;
+    let info = remote_chain(deps, &master, &chain, ChainSlaveKind::WasmIbc)?;
+    if info.connection_id.as_ref() != Some(&channel.connection_id) {
+        return Err(ContractError::UnexpectedConnection {});
+    }
+    Ok(chain)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<IbcChannelOpenResponse, ContractError> {
+    let channel = msg.channel();
+    validate_channel(channel, msg.counterparty_version())?;
+    expected_chain(deps.as_ref(), channel)?;
+    Ok(Some(Ibc3ChannelOpenResponse { version: IBC_VERSION.to_string() }))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel = msg.channel();
+    validate_channel(channel, msg.counterparty_version())?;
+    let chain = expected_chain(deps.as_ref(), channel)?;
+    let port_id = &channel.counterparty_endpoint.port_id;
+    EXPECTED_SLAVES.remove(deps.storage, port_id);
+    // Address on another chain, cannot be validated here
+    let addr = port_id.strip_prefix("wasm.").unwrap_or(port_id);
+    let slave = SlaveInfo {
+        addr: Addr::unchecked(addr),
+        kind: SlaveKind::Ibc {
+            channel_id: channel.endpoint.channel_id.clone(),
+        },
+        code_version: None,
+        registered_at: env.block.time,
+    };
+    SLAVES.save(deps.storage, &chain, &slave)?;
+    Ok(IbcBasicResponse::new()
+        .add_attribute("contract", "host")
+        .add_attribute("action", "register_slave")
+        .add_attribute("chain", chain)
+        .add_attribute("channel_id", &channel.endpoint.channel_id))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel_id = &msg.channel().endpoint.channel_id;
+    let chains = SLAVES
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref().map_or(true, |(_, slave)| {
+                slave.kind.channel_id() == Some(channel_id.as_str())
+            })
+        })
+        .map(|item| item.map(|(chain, _)| chain))
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut response = IbcBasicResponse::new()
+        .add_attribute("contract", "host")
+        .add_attribute("action", "ibc_channel_close")
+        .add_attribute("channel_id", channel_id);
+    for chain in chains {
+        SLAVES.remove(deps.storage, &chain);
+        SYNCED_CONFIGS.remove(deps.storage, &chain);
+        response = response.add_attribute("chain", chain);
+    }
+    Ok(response)
+}
+
+/// Errors are returned as acknowledgements, so that the packet is never
+/// stuck on the relayer.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, Never> {
+    receive(deps, env, msg).or_else(|err| {
+        Ok(IbcReceiveResponse::new()
+            .add_attribute("contract", "host")
+            .add_attribute("method", "ibc_packet_receive")
+            .add_attribute("error", err.to_string())
+            .set_ack(StdAck::error(err.to_string())))
+    })
+}
+
+fn receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    // Only our remote slaves speak for their chain
+    let chain = channel_chain(deps.storage, &msg.packet.dest.channel_id)?
+        .ok_or(ContractError::Unauthorized {})?;
+    match from_json(&msg.packet.data)? {
+        HostPacket::ApproveRecovery { guardian, target } => {
+            let target_addr = deps.api.addr_validate(&target)?;
+            let info = MessageInfo {
+                sender: remote_voter(&chain, &guardian),
+                funds: vec![],
+            };
+            let response = crate::contract::execute(
+                deps,
+                env,
+                info,
+                ExecuteMsg::ApproveSocialRecovery { target_addr },
+            )?;
+            Ok(IbcReceiveResponse::new()
+                .add_attribute("contract", "host")
+                .add_attribute("method", "ibc_packet_receive")
+                .add_attribute("chain", chain)
+                .add_attributes(response.attributes)
+                .add_submessages(response.messages)
+                .add_events(response.events)
+                .set_ack(StdAck::success(response.data.unwrap_or_default())))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let packet = &msg.original_packet;
+    let ack = &msg.acknowledgement.data;
+    if let Some(version) = config_version(packet) {
+        return config_ack(
+            deps,
+            &packet.src.channel_id,
+            version,
+            from_json(ack)?,
+        );
+    }
+    // Slave changes are reverted on error, but the attempt is kept
+    let (status, failed_msgs, error) = match from_json(ack)? {
+        SlaveAck::Result(batch) => (
+            TxStatus::Succeeded,
+            batch.failed.iter().map(|f| f.index).collect(),
+            None,
+        ),
+        SlaveAck::Error(err) => (TxStatus::Failed, vec![], Some(err)),
+    };
+    let tx = history::complete(
+        deps.storage,
+        packet_tx_id(packet)?,
+        status,
+        failed_msgs,
+        error,
+    )?;
+    Ok(tx_response("ibc_packet_ack", &tx))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    if let Some(version) = config_version(&msg.packet) {
+        return config_timeout(
+            deps,
+            env,
+            &msg.packet.src.channel_id,
+            version,
+        );
+    }
+    let tx = history::complete(
+        deps.storage,
+        packet_tx_id(&msg.packet)?,
+        TxStatus::Failed,
+        vec![],
+        Some("Packet timed out.".to_string()),
+    )?;
+    Ok(tx_response("ibc_packet_timeout", &tx))
+}
+
+fn packet_tx_id(packet: &IbcPacket) -> StdResult<u64> {
+    match from_json(&packet.data)? {
+        SlavePacket::ExecuteBatch { tx_id, .. } => Ok(tx_id),
+        SlavePacket::SyncConfig { .. } => {
+            Err(StdError::generic_err("Not a transaction packet."))
+        }
+    }
+}
+
+/// Config version carried by a [`SlavePacket::SyncConfig`] packet.
+fn config_version(packet: &IbcPacket) -> Option<u64> {
+    match from_json(&packet.data) {
+        Ok(SlavePacket::SyncConfig { version, .. }) => Some(version),
+        _ => None,
+    }
+}
+
+fn config_ack(
+    deps: DepsMut,
+    channel_id: &str,
+    version: u64,
+    ack: StdAck,
+) -> Result<IbcBasicResponse, ContractError> {
+    let mut response = IbcBasicResponse::new()
+        .add_attribute("contract", "host")
+        .add_attribute("method", "ibc_packet_ack")
+        .add_attribute("config_version", version.to_string());
+    if let StdAck::Error(error) = ack {
+        return Ok(response.add_attribute("error", error));
+    }
+    // Slave may have been replaced while the packet was in flight
+    if let Some(chain) = channel_chain(deps.storage, channel_id)? {
+        // Acknowledgements of an unordered channel may arrive in any order
+        if SYNCED_CONFIGS.may_load(deps.storage, &chain)? < Some(version) {
+            SYNCED_CONFIGS.save(deps.storage, &chain, &version)?;
+        }
+        response = response.add_attribute("chain", chain);
+    }
+    Ok(response)
+}
+
+/// Only the latest config is sent again, older ones are superseded.
+fn config_timeout(
+    deps: DepsMut,
+    env: Env,
+    channel_id: &str,
+    version: u64,
+) -> Result<IbcBasicResponse, ContractError> {
+    let response = IbcBasicResponse::new()
+        .add_attribute("contract", "host")
+        .add_attribute("method", "ibc_packet_timeout")
+        .add_attribute("config_version", version.to_string());
+    let latest = CONFIG_VERSION.may_load(deps.storage)?.unwrap_or_default();
+    match channel_chain(deps.storage, channel_id)? {
+        Some(chain) if version == latest => {
+            let msg = sync_config(
+                deps.storage,
+                &env,
+                &chain,
+                channel_id.to_string(),
+            )?;
+            Ok(response
+                .add_attribute("chain", chain)
+                .add_attribute("retry", "true")
+                .add_message(msg))
+        }
+        _ => Ok(response),
+    }
+}
+
+/// Chain of the remote slave connected over `channel_id`.
+fn channel_chain(
+    storage: &dyn Storage,
+    channel_id: &str,
+) -> StdResult<Option<String>> {
+    let slave = SLAVES
+        .range(storage, None, None, Order::Ascending)
+        .find(|item| {
+            item.as_ref().map_or(true, |(_, slave)| {
+                matches!(
+                    &slave.kind,
+                    SlaveKind::Ibc { channel_id: id } if id == channel_id
+                )
+            })
+        })
+        .transpose()?;
+    Ok(slave.map(|(chain, _)| chain))
+}
+
+/// Report the outcome of a remote transaction in the wallet events.
+pub fn tx_response(method: &str, tx: &Transaction) -> IbcBasicResponse {
+    let status = match tx.status {
+        TxStatus::Pending => "pending",
+        TxStatus::Succeeded => "succeeded",
+        TxStatus::Failed => "failed",
+    };
+    let response = IbcBasicResponse::new()
+        .add_attribute("contract", "host")
+        .add_attribute("method", method)
+        .add_attribute("tx_id", tx.id.to_string())
+        .add_attribute("status", status);
+    match &tx.error {
+        Some(error) => response.add_attribute("error", error),
+        None => response,
+    }
+}
+
+/// Send the current config to the remote slave of `chain`.
+pub fn sync_config(
+    storage: &dyn Storage,
+    env: &Env,
+    chain: &str,
+    channel_id: String,
+) -> StdResult<IbcMsg> {
+    let state = STATE.load(storage)?;
+    let config = SlaveConfig {
+        owner: state.owner.to_string(),
+        guardians: state
+            .recovery_pool
+            .iter()
+            .filter(|m| m.chain.as_deref() == Some(chain))
+            .map(|m| m.addr.to_string())
+            .collect(),
+    };
+    let packet = SlavePacket::SyncConfig {
+        version: CONFIG_VERSION.may_load(storage)?.unwrap_or_default(),
+        config,
+    };
+    Ok(IbcMsg::SendPacket {
+        channel_id,
+        data: to_json_binary(&packet)?,
+        timeout: IbcTimeout::with_timestamp(
+            env.block.time.plus_seconds(PACKET_LIFETIME),
+        ),
+    })
+}
+
+/// Bump the config version and push the config to every remote slave.
+pub fn sync_slaves(
+    storage: &mut dyn Storage,
+    env: &Env,
+) -> StdResult<Vec<IbcMsg>> {
+    let version = CONFIG_VERSION.may_load(storage)?.unwrap_or_default();
+    CONFIG_VERSION.save(storage, &(version + 1))?;
+    let slaves = SLAVES
+        .range(storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((
+                chain,
+                SlaveInfo { kind: SlaveKind::Ibc { channel_id }, .. },
+            )) => Some(Ok((chain, channel_id))),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    slaves
+        .into_iter()
+        .map(|(chain, channel_id)| {
+            sync_config(storage, env, &chain, channel_id)
+        })
+        .collect()
+}
+
+/// Send `msgs` to a remote slave and record them in the history, to be
+/// completed by the acknowledgement or timeout.
+pub fn send_batch(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    channel_id: String,
+    msgs: Vec<CosmosMsg>,
+    mode: BatchMode,
+) -> StdResult<(u64, IbcMsg)> {
+    let tx_id = history::record(storage, env, sender, &msgs)?;
+    let packet = SlavePacket::ExecuteBatch { tx_id, msgs, mode };
+    Ok((
+        tx_id,
+        IbcMsg::SendPacket {
+            channel_id,
+            data: to_json_binary(&packet)?,
+            timeout: IbcTimeout::with_timestamp(
+                env.block.time.plus_seconds(PACKET_LIFETIME),
+            ),
+        },
+    ))
+}
@@ -0,0 +1,330 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_json, to_json_string, Addr, BankMsg, Binary, Coin, CosmosMsg,
+    DepsMut, DistributionMsg, Env, GovMsg, IbcAcknowledgement, IbcMsg,
+    IbcPacket, QuerierWrapper, QueryRequest, Response, StakingMsg, StdAck,
+    StdResult, Storage, VoteOption,
+};
+
+use crate::error::ContractError;
+use crate::state::TxStatus;
+use crate::{history, ibc};
+
+/// Port prefix of the ICS-27 controller module, followed by the owner.
+pub const ICA_CONTROLLER_PORT_PREFIX: &str = "icacontroller-";
+/// Interchain account transactions not relayed within an hour fail.
+pub const ICA_PACKET_LIFETIME: u64 = 60 * 60;
+pub const REGISTER_ICA_TYPE_URL: &str =
+    "/ibc.applications.interchain_accounts.controller.v1.MsgRegisterInterchainAccount";
+pub const SEND_TX_TYPE_URL: &str =
+    "/ibc.applications.interchain_accounts.controller.v1.MsgSendTx";
+pub const ICA_ADDRESS_QUERY: &str =
+    "/ibc.applications.interchain_accounts.controller.v1.Query/InterchainAccount";
+/// `InterchainAccountPacketData.type` of a transaction.
+const TYPE_EXECUTE_TX: u64 = 1;
+
+#[cw_serde]
+pub struct InterchainAccountResponse {
+    pub address: String,
+}
+
+/// Packet data of the controller module, JSON encoded on the wire.
+#[cw_serde]
+pub struct IcaPacketData {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub data: Binary,
+    pub memo: String,
+}
+
+/// Memo of our packets: the ibc-go callbacks middleware reports the
+/// outcome to `src_callback`, `tx_id` ties it to the wallet history.
+#[cw_serde]
+pub struct IcaMemo {
+    pub src_callback: CallbackAddress,
+    pub tx_id: u64,
+}
+
+#[cw_serde]
+pub struct CallbackAddress {
+    pub address: Addr,
+}
+
+/// Outcome of a packet sent by the controller module on our behalf, as
+/// delivered by the ibc-go callbacks middleware.
+#[cw_serde]
+pub enum IbcSourceCallbackMsg {
+    Acknowledgement(IbcAckCallbackMsg),
+    Timeout(IbcTimeoutCallbackMsg),
+}
+
+#[cw_serde]
+pub struct IbcAckCallbackMsg {
+    pub acknowledgement: IbcAcknowledgement,
+    pub original_packet: IbcPacket,
+    pub relayer: Addr,
+}
+
+#[cw_serde]
+pub struct IbcTimeoutCallbackMsg {
+    pub packet: IbcPacket,
+    pub relayer: Addr,
+}
+
+/// Ask the controller module to open an interchain account owned by
+/// `owner` over `connection_id`. Opening it again after a timeout closed
+/// the ordered channel keeps the same account.
+pub fn register(owner: &Addr, connection_id: &str) -> CosmosMsg {
+    let mut buf = vec![];
+    put_string(&mut buf, 1, owner.as_str());
+    put_string(&mut buf, 2, connection_id);
+    // Empty version, the controller proposes the default metadata
+    CosmosMsg::Stargate {
+        type_url: REGISTER_ICA_TYPE_URL.to_string(),
+        value: buf.into(),
+    }
+}
+
+/// Address of the interchain account of `owner` over `connection_id`,
+/// known once the channel handshake completed.
+pub fn account_address(
+    querier: &QuerierWrapper,
+    owner: &Addr,
+    connection_id: &str,
+) -> StdResult<String> {
+    let mut buf = vec![];
+    put_string(&mut buf, 1, owner.as_str());
+    put_string(&mut buf, 2, connection_id);
+    let res: InterchainAccountResponse =
+        querier.query(&QueryRequest::Stargate {
+            path: ICA_ADDRESS_QUERY.to_string(),
+            data: buf.into(),
+        })?;
+    Ok(res.address)
+}
+
+/// Send `msgs` as a single transaction of the interchain account
+/// `address` and record them in the history, to be completed by
+/// [`ibc_source_callback`].
+pub fn send_tx(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    connection_id: &str,
+    address: &str,
+    msgs: Vec<CosmosMsg>,
+) -> Result<(u64, CosmosMsg), ContractError> {
+    let mut tx = vec![];
+    for msg in msgs.iter() {
+        let (type_url, value) = encode_msg(msg, address)?;
+        let mut any = vec![];
+        put_bytes(&mut any, 1, type_url.as_bytes());
+        put_bytes(&mut any, 2, &value);
+        // CosmosTx.messages
+        put_bytes(&mut tx, 1, &any);
+    }
+    let tx_id = history::record(storage, env, sender, &msgs)?;
+    let memo = IcaMemo {
+        src_callback: CallbackAddress {
+            address: env.contract.address.clone(),
+        },
+        tx_id,
+    };
+    let mut packet = vec![];
+    put_varint(&mut packet, 1, TYPE_EXECUTE_TX);
+    put_bytes(&mut packet, 2, &tx);
+    put_string(&mut packet, 3, &to_json_string(&memo)?);
+    let mut buf = vec![];
+    put_string(&mut buf, 1, env.contract.address.as_str());
+    put_string(&mut buf, 2, connection_id);
+    put_bytes(&mut buf, 3, &packet);
+    put_varint(&mut buf, 4, ICA_PACKET_LIFETIME * 1_000_000_000);
+    Ok((
+        tx_id,
+        CosmosMsg::Stargate {
+            type_url: SEND_TX_TYPE_URL.to_string(),
+            value: buf.into(),
+        },
+    ))
+}
+
+// cosmwasm-std 1.5 has no `entry_point` support for the callbacks entry
+// point, which takes the same arguments as `sudo`.
+#[cfg(all(target_arch = "wasm32", not(feature = "library")))]
+mod __wasm_export_ibc_source_callback {
+    #[no_mangle]
+    extern "C" fn ibc_source_callback(ptr0: u32, ptr1: u32) -> u32 {
+        cosmwasm_std::do_sudo(&super::ibc_source_callback, ptr0, ptr1)
+    }
+}
+
+/// Complete the transaction of an interchain account packet.
+pub fn ibc_source_callback(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcSourceCallbackMsg,
+) -> Result<Response, ContractError> {
+    let (packet, status, error) = match &msg {
+        IbcSourceCallbackMsg::Acknowledgement(ack) => {
+            match from_json(&ack.acknowledgement.data)? {
+                StdAck::Success(_) => {
+                    (&ack.original_packet, TxStatus::Succeeded, None)
+                }
+                StdAck::Error(err) => {
+                    (&ack.original_packet, TxStatus::Failed, Some(err))
+                }
+            }
+        }
+        // The ordered channel is closed, it has to be registered again
+        IbcSourceCallbackMsg::Timeout(timeout) => (
+            &timeout.packet,
+            TxStatus::Failed,
+            Some("Packet timed out.".to_string()),
+        ),
+    };
+    // Only our own controller port sends packets with our transactions
+    let owner =
+        format!("{ICA_CONTROLLER_PORT_PREFIX}{}", env.contract.address);
+    if packet.src.port_id != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let data: IcaPacketData = from_json(&packet.data)?;
+    let memo: IcaMemo = from_json(data.memo.as_bytes())?;
+    let tx =
+        history::complete(deps.storage, memo.tx_id, status, vec![], error)?;
+    let response = ibc::tx_response("ibc_source_callback", &tx);
+    Ok(Response::new().add_attributes(response.attributes))
+}
+
+/// Protobuf `Any` type url and value of `msg` sent by `sender`.
+///
+/// Chains driven through ICA have no CosmWasm, so only the SDK messages
+/// are supported and encoded by hand.
+fn encode_msg(
+    msg: &CosmosMsg,
+    sender: &str,
+) -> Result<(String, Vec<u8>), ContractError> {
+    let mut buf = vec![];
+    let type_url = match msg {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+            put_string(&mut buf, 1, sender);
+            put_string(&mut buf, 2, to_address);
+            for coin in amount {
+                put_bytes(&mut buf, 3, &encode_coin(coin));
+            }
+            "/cosmos.bank.v1beta1.MsgSend"
+        }
+        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
+            put_string(&mut buf, 1, sender);
+            put_string(&mut buf, 2, validator);
+            put_bytes(&mut buf, 3, &encode_coin(amount));
+            "/cosmos.staking.v1beta1.MsgDelegate"
+        }
+        CosmosMsg::Staking(StakingMsg::Undelegate { validator, amount }) => {
+            put_string(&mut buf, 1, sender);
+            put_string(&mut buf, 2, validator);
+            put_bytes(&mut buf, 3, &encode_coin(amount));
+            "/cosmos.staking.v1beta1.MsgUndelegate"
+        }
+        CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator,
+            dst_validator,
+            amount,
+        }) => {
+            put_string(&mut buf, 1, sender);
+            put_string(&mut buf, 2, src_validator);
+            put_string(&mut buf, 3, dst_validator);
+            put_bytes(&mut buf, 4, &encode_coin(amount));
+            "/cosmos.staking.v1beta1.MsgBeginRedelegate"
+        }
+        CosmosMsg::Distribution(DistributionMsg::SetWithdrawAddress {
+            address,
+        }) => {
+            put_string(&mut buf, 1, sender);
+            put_string(&mut buf, 2, address);
+            "/cosmos.distribution.v1beta1.MsgSetWithdrawAddress"
+        }
+        CosmosMsg::Distribution(
+            DistributionMsg::WithdrawDelegatorReward { validator },
+        ) => {
+            put_string(&mut buf, 1, sender);
+            put_string(&mut buf, 2, validator);
+            "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward"
+        }
+        CosmosMsg::Gov(GovMsg::Vote { proposal_id, vote }) => {
+            put_varint(&mut buf, 1, *proposal_id);
+            put_string(&mut buf, 2, sender);
+            let option = match vote {
+                VoteOption::Yes => 1,
+                VoteOption::Abstain => 2,
+                VoteOption::No => 3,
+                VoteOption::NoWithVeto => 4,
+            };
+            put_varint(&mut buf, 3, option);
+            "/cosmos.gov.v1beta1.MsgVote"
+        }
+        CosmosMsg::Ibc(IbcMsg::Transfer {
+            channel_id,
+            to_address,
+            amount,
+            timeout,
+        }) => {
+            put_string(&mut buf, 1, "transfer");
+            put_string(&mut buf, 2, channel_id);
+            put_bytes(&mut buf, 3, &encode_coin(amount));
+            put_string(&mut buf, 4, sender);
+            put_string(&mut buf, 5, to_address);
+            if let Some(block) = timeout.block() {
+                let mut height = vec![];
+                put_varint(&mut height, 1, block.revision);
+                put_varint(&mut height, 2, block.height);
+                put_bytes(&mut buf, 6, &height);
+            }
+            if let Some(timestamp) = timeout.timestamp() {
+                put_varint(&mut buf, 7, timestamp.nanos());
+            }
+            "/ibc.applications.transfer.v1.MsgTransfer"
+        }
+        // Already encoded, the sender must be the interchain account
+        CosmosMsg::Stargate { type_url, value } => {
+            return Ok((type_url.clone(), value.to_vec()));
+        }
+        _ => return Err(ContractError::UnsupportedIcaMessage {}),
+    };
+    Ok((type_url.to_string(), buf))
+}
+
+fn encode_coin(coin: &Coin) -> Vec<u8> {
+    let mut buf = vec![];
+    put_string(&mut buf, 1, &coin.denom);
+    put_string(&mut buf, 2, &coin.amount.to_string());
+    buf
+}
+
+fn put_raw_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_varint(buf: &mut Vec<u8>, field: u64, value: u64) {
+    // Default values are omitted in proto3
+    if value != 0 {
+        put_raw_varint(buf, field << 3);
+        put_raw_varint(buf, value);
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u64, value: &[u8]) {
+    put_raw_varint(buf, (field << 3) | 2);
+    put_raw_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+fn put_string(buf: &mut Vec<u8>, field: u64, value: &str) {
+    if !value.is_empty() {
+        put_bytes(buf, field, value.as_bytes());
+    }
+}
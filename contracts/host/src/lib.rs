@@ -1,6 +1,13 @@
 pub mod contract;
 mod error;
+mod history;
+pub mod ibc;
+pub mod ica;
+mod migrations;
 pub mod msg;
+mod session;
+mod signed;
+mod spending;
 pub mod state;
 
 pub use crate::error::ContractError;
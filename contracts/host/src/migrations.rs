@@ -0,0 +1,98 @@
+use cosmwasm_std::{Addr, Env, QuerierWrapper, StdResult, Storage};
+use cw_storage_plus::{Deque, Item};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    LimitExceededAction, RecoveryMember, SlaveInfo, State, PROPOSAL_COUNT,
+    SLAVES, STATE, TX_COUNT,
+};
+
+/// Layout of hosts deployed as 0.1.x.
+mod v0_1 {
+    use super::*;
+
+    #[derive(
+        Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema,
+    )]
+    pub struct State {
+        pub owner: Addr,
+        pub master: Addr,
+        pub potential_owner: Option<Addr>,
+        pub recovery_method: Option<String>,
+        pub recovery_pool: Vec<Addr>,
+        pub approval_pool: Vec<Addr>,
+        pub recovery_approvals_needed: u32,
+        pub transfer_ownership_approvals_needed: u32,
+        pub chain: String,
+    }
+
+    pub const STATE: Item<State> = Item::new("state");
+}
+
+/// Slave registry of hosts deployed up to 0.2.x.
+mod v0_2 {
+    use super::*;
+
+    pub const SLAVES: Deque<(String, Addr)> = Deque::new("slaves");
+}
+
+pub fn from_v0_1(
+    storage: &mut dyn Storage,
+    env: &Env,
+    recovery_delay: u64,
+    recovery_lifetime: u64,
+) -> StdResult<()> {
+    let old = v0_1::STATE.load(storage)?;
+    let state = State {
+        recovery_pool: old
+            .recovery_pool
+            .into_iter()
+            .map(|addr| RecoveryMember {
+                addr,
+                weight: 1,
+                required: false,
+                pubkey: None,
+                chain: None,
+            })
+            .collect(),
+        proposal_approvals_needed: (old.approval_pool.len() as u32).max(1),
+        approval_pool: old.approval_pool,
+        limit_exceeded_action: LimitExceededAction::Reject,
+        recovery_initiator: None,
+        recovery_lifetime,
+        // Give a process that is already running the usual deadline
+        recovery_expires_at: old
+            .potential_owner
+            .as_ref()
+            .map(|_| env.block.time.plus_seconds(recovery_lifetime)),
+        recovery_delay,
+        recovery_unlocks_at: None,
+        owner_pubkey: None,
+        recovery_nonce: 0,
+        owner: old.owner,
+        master: old.master,
+        potential_owner: old.potential_owner,
+        recovery_method: old.recovery_method,
+        recovery_approvals_needed: old.recovery_approvals_needed,
+        transfer_ownership_approvals_needed: old
+            .transfer_ownership_approvals_needed,
+        chain: old.chain,
+    };
+    STATE.save(storage, &state)?;
+    PROPOSAL_COUNT.save(storage, &0)?;
+    TX_COUNT.save(storage, &0)
+}
+
+pub fn from_v0_2(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+) -> StdResult<()> {
+    // Registration time is unknown, the migration is the best we have
+    while let Some((chain, addr)) = v0_2::SLAVES.pop_front(storage)? {
+        let slave = SlaveInfo::new(querier, addr, env.block.time);
+        SLAVES.save(storage, &chain, &slave)?;
+    }
+    Ok(())
+}
@@ -1,30 +1,183 @@
-use std::collections::HashMap;
-
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, CosmosMsg};
+use cosmwasm_std::{Addr, Binary, Coin, CosmosMsg, Timestamp, Uint128};
+
+use crate::state::{
+    GuardianPubkey, LimitExceededAction, MsgKind, ProposalStatus,
+    RecoveryMember, SlaveKind, Transaction,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
-    pub recovery_pool: Vec<Addr>,
+    pub recovery_pool: Vec<RecoveryMember>,
     pub approval_pool: Vec<Addr>,
     pub recovery_approvals_needed: u32,
     pub transfer_ownership_approvals_needed: u32,
+    /// Defaults to the size of `approval_pool`, at least 1.
+    pub proposal_approvals_needed: Option<u32>,
+    /// Seconds between social recovery quorum and ownership transfer,
+    /// defaults to two days.
+    pub recovery_delay: Option<u64>,
+    /// Seconds guardians have to reach quorum, defaults to one week.
+    pub recovery_lifetime: Option<u64>,
+    /// secp256k1 key allowed to sign `ExecuteSigned` payloads.
+    pub owner_pubkey: Option<Binary>,
     pub owner: Addr,
     pub chain: String,
 }
 
+#[cw_serde]
+pub struct MigrateMsg {}
+
 #[cw_serde]
 pub enum ExecuteMsg {
-    AddRecoveryMember { member: Addr },
-    AddApprovalMember { member: Addr },
-    RemoveRecoveryMember { member: Addr },
-    RemoveApprovalMember { member: Addr },
-    RegisterSlave { chain: String, addr: Addr },
-    ExecuteSameChain { body_proxy: CosmosMsg },
-    BeginSocialRecovery { target_addr: Addr },
-    ApproveSocialRecovery { target_addr: Addr },
-    BeginTransferOwnership { target_addr: Addr },
-    ApproveTransferOwnership { target_addr: Addr },
+    AddRecoveryMember {
+        member: Addr,
+        weight: u32,
+        required: bool,
+        pubkey: Option<GuardianPubkey>,
+        /// Set for a guardian approving from a remote chain through the
+        /// slave there, `member` is then an address on that chain.
+        chain: Option<String>,
+    },
+    /// Sent by a guardian to set the key of their off-chain approvals.
+    UpdateGuardianPubkey {
+        pubkey: Option<GuardianPubkey>,
+    },
+    AddApprovalMember {
+        member: Addr,
+    },
+    /// Remote guardians are removed as `chain/addr`.
+    RemoveRecoveryMember {
+        member: Addr,
+    },
+    RemoveApprovalMember {
+        member: Addr,
+    },
+    RegisterSlave {
+        chain: String,
+        addr: Addr,
+    },
+    UnregisterSlave {
+        chain: String,
+    },
+    /// Accept a channel from the slave bound to `port_id` on a remote
+    /// `chain`.
+    AllowRemoteSlave {
+        chain: String,
+        port_id: String,
+    },
+    /// Open an ICS-27 interchain account on `chain`, a chain the master
+    /// registered for interchain accounts.
+    RegisterInterchainAccount {
+        chain: String,
+    },
+    /// Register the interchain account of `chain` as its slave once its
+    /// channel is open. Anyone may call this.
+    ConfirmInterchainAccount {
+        chain: String,
+    },
+    /// Point the wallet to another same-chain slave created by master,
+    /// remote slaves are replaced by reconnecting their channel.
+    ReplaceSlave {
+        chain: String,
+        addr: Addr,
+    },
+    ExecuteSameChain {
+        body_proxy: CosmosMsg,
+    },
+    ExecuteBatch {
+        msgs: Vec<CosmosMsg>,
+        mode: BatchMode,
+    },
+    /// Push the current config to the remote slave of `chain` again.
+    SyncSlave {
+        chain: String,
+    },
+    /// Execute `msgs` atomically on the slave registered for `chain`.
+    ExecuteOnChain {
+        chain: String,
+        msgs: Vec<CosmosMsg>,
+    },
+    /// Relay an owner-signed JSON encoded `SignedPayload`.
+    ExecuteSigned {
+        payload: Binary,
+        signature: Binary,
+    },
+    UpdateOwnerPubkey {
+        pubkey: Option<Binary>,
+    },
+    BeginSocialRecovery {
+        target_addr: Addr,
+    },
+    ApproveSocialRecovery {
+        target_addr: Addr,
+    },
+    /// Approve recovery on behalf of guardians that signed the JSON
+    /// encoded `RecoveryApproval` in `payload`. Starts the process if
+    /// none is active.
+    SubmitRecoveryApprovals {
+        payload: Binary,
+        signatures: Vec<GuardianSignature>,
+    },
+    RetractRecoveryApproval {},
+    CancelRecovery {},
+    VetoRecovery {},
+    FinalizeRecovery {},
+    UpdateRecoveryDelay {
+        delay: u64,
+    },
+    UpdateRecoveryLifetime {
+        lifetime: u64,
+    },
+    BeginTransferOwnership {
+        target_addr: Addr,
+    },
+    ApproveTransferOwnership {
+        target_addr: Addr,
+    },
+    ProposeTransaction {
+        msgs: Vec<CosmosMsg>,
+        expires_in: Option<u64>,
+    },
+    ApproveProposal {
+        proposal_id: u64,
+    },
+    CancelProposal {
+        proposal_id: u64,
+    },
+    UpdateProposalApprovalsNeeded {
+        approvals_needed: u32,
+    },
+    SetSpendingLimit {
+        denom: String,
+        period: u64,
+        amount: Uint128,
+    },
+    RemoveSpendingLimit {
+        denom: String,
+        period: u64,
+    },
+    /// Tightening the limits is immediate. Raising or removing a limit and
+    /// switching to `Escalate` only take effect through
+    /// `ApplyLimitChanges` after a timelock, which gives guardians time to
+    /// recover a wallet whose owner key leaked.
+    UpdateLimitExceededAction {
+        action: LimitExceededAction,
+    },
+    /// Apply loosened limits whose timelock passed, callable by anyone.
+    ApplyLimitChanges {},
+    /// Session keys can never call the slave itself, whose settings like
+    /// the escape hatch only the owner changes.
+    AddSessionKey {
+        key: Addr,
+        expires_in: u64,
+        spend_limit: Vec<Coin>,
+        allowed_msgs: Vec<MsgKind>,
+        allowed_contracts: Vec<Addr>,
+    },
+    RevokeSessionKey {
+        key: Addr,
+    },
 }
 
 #[cw_serde]
@@ -34,31 +187,287 @@ pub enum QueryMsg {
     GetRecoveryPool {},
 
     #[returns(GetSlavesResponse)]
-    GetSlaves {},
+    GetSlaves { start_after: Option<String>, limit: Option<u32> },
 
     #[returns(GetSlaveResponse)]
     GetSlave { chain: String },
+
+    #[returns(SlaveSyncStatusResponse)]
+    GetSlaveSyncStatus { chain: String },
+
+    #[returns(ProposalResponse)]
+    GetProposal { proposal_id: u64 },
+
+    #[returns(ListProposalsResponse)]
+    ListProposals { start_after: Option<u64>, limit: Option<u32> },
+
+    #[returns(GetSpendingAllowanceResponse)]
+    GetSpendingAllowance { denom: Option<String> },
+
+    /// Newest first.
+    #[returns(GetTransactionsResponse)]
+    GetTransactions { start_after: Option<u64>, limit: Option<u32> },
+
+    /// Nonces up to the last accepted one count as used.
+    #[returns(NonceResponse)]
+    GetNonce { nonce: u64 },
+
+    #[returns(SessionKeyResponse)]
+    GetSessionKey { key: Addr },
+
+    #[returns(ListSessionKeysResponse)]
+    ListSessionKeys { start_after: Option<Addr>, limit: Option<u32> },
 }
 
 #[cw_serde]
 pub struct GetRecoveryPoolResponse {
-    pub members: Vec<Addr>,
+    pub members: Vec<RecoveryMember>,
     pub recovery_approvals_count: u32,
     pub transfer_approvals_count: u32,
     pub recovery_progress: u32,
     pub recovery_method: Option<String>,
     pub new_owner: Option<Addr>,
+    pub recovery_initiator: Option<Addr>,
+    pub recovery_expires_at: Option<Timestamp>,
+    pub recovery_delay: u64,
+    pub recovery_unlocks_at: Option<Timestamp>,
+    /// To be included in off-chain guardian approvals.
+    pub recovery_nonce: u64,
+}
+#[cw_serde]
+pub struct SlaveResponse {
+    pub chain: String,
+    pub addr: Addr,
+    pub kind: SlaveKind,
+    pub code_version: Option<String>,
+    pub registered_at: Timestamp,
 }
 #[cw_serde]
 pub struct GetSlavesResponse {
-    pub slaves: HashMap<String, Addr>,
+    pub slaves: Vec<SlaveResponse>,
 }
 #[cw_serde]
 pub struct GetSlaveResponse {
     pub slave: Option<Addr>,
 }
+#[cw_serde]
+pub struct SlaveSyncStatusResponse {
+    /// Current config version of the wallet.
+    pub version: u64,
+    /// Latest version acknowledged by the slave, if any.
+    pub synced_version: Option<u64>,
+    pub in_sync: bool,
+}
+
+#[cw_serde]
+pub struct ProposalResponse {
+    pub id: u64,
+    pub proposer: Addr,
+    pub msgs: Vec<CosmosMsg>,
+    pub approvals: Vec<Addr>,
+    pub approvals_needed: u32,
+    pub status: ProposalStatus,
+    pub expires_at: Timestamp,
+}
+#[cw_serde]
+pub struct ListProposalsResponse {
+    pub proposals: Vec<ProposalResponse>,
+}
+
+#[cw_serde]
+pub struct SpendingAllowance {
+    pub denom: String,
+    pub period: u64,
+    pub limit: Uint128,
+    pub spent: Uint128,
+    pub remaining: Uint128,
+}
+#[cw_serde]
+pub struct PendingLimitChange {
+    pub denom: String,
+    pub period: u64,
+    /// `None` removes the limit.
+    pub amount: Option<Uint128>,
+    pub effective_at: Timestamp,
+}
+#[cw_serde]
+pub struct GetSpendingAllowanceResponse {
+    pub allowances: Vec<SpendingAllowance>,
+    pub limit_exceeded_action: LimitExceededAction,
+    pub pending_limits: Vec<PendingLimitChange>,
+    /// When the switch to `Escalate` takes effect.
+    pub pending_escalation: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct SignedPayload {
+    pub chain_id: String,
+    pub host: Addr,
+    /// Must be greater than the last accepted nonce.
+    pub nonce: u64,
+    pub expires_at: Timestamp,
+    pub msgs: Vec<CosmosMsg>,
+    /// Paid from the wallet to whoever relays the payload.
+    pub relayer_fee: Option<Coin>,
+}
+
+#[cw_serde]
+pub struct GetTransactionsResponse {
+    pub transactions: Vec<Transaction>,
+}
+
+#[cw_serde]
+pub struct RecoveryApproval {
+    pub chain_id: String,
+    pub host: Addr,
+    pub recovery_nonce: u64,
+    pub target_addr: Addr,
+    pub expires_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct GuardianSignature {
+    pub guardian: Addr,
+    pub signature: Binary,
+}
+
+#[cw_serde]
+pub struct NonceResponse {
+    pub nonce: u64,
+    pub used: bool,
+}
+
+#[cw_serde]
+pub struct SessionKeyResponse {
+    pub key: Addr,
+    pub expires_at: Timestamp,
+    pub spend_limit: Vec<Coin>,
+    pub spent: Vec<Coin>,
+    pub allowed_msgs: Vec<MsgKind>,
+    pub allowed_contracts: Vec<Addr>,
+}
+#[cw_serde]
+pub struct ListSessionKeysResponse {
+    pub keys: Vec<SessionKeyResponse>,
+}
+
+#[cw_serde]
+pub enum BatchMode {
+    /// Any failure reverts the whole batch.
+    Atomic,
+    /// Failed messages are reverted and reported, the rest still runs.
+    BestEffort,
+}
+
+/// Response data of `ExecuteBatch`, as reported by the slave.
+#[cw_serde]
+pub struct BatchResult {
+    pub total: u32,
+    pub failed: Vec<BatchFailure>,
+}
+
+#[cw_serde]
+pub struct BatchFailure {
+    pub index: u32,
+    pub error: String,
+}
+
+#[cw_serde]
+pub enum SlaveExecuteMsg {
+    ExecuteBatch { msgs: Vec<CosmosMsg>, mode: BatchMode },
+}
+
+/// Packets sent to remote slaves.
+#[cw_serde]
+pub enum SlavePacket {
+    ExecuteBatch {
+        tx_id: u64,
+        msgs: Vec<CosmosMsg>,
+        mode: BatchMode,
+    },
+    /// Acknowledged with a [`cosmwasm_std::StdAck`], slaves keep the
+    /// highest `version` received.
+    SyncConfig {
+        version: u64,
+        config: SlaveConfig,
+    },
+}
+
+/// Wallet config mirrored by a remote slave.
+#[cw_serde]
+pub struct SlaveConfig {
+    /// Owner of the wallet on the host chain.
+    pub owner: String,
+    /// Guardians on the slave chain allowed to approve recovery there.
+    pub guardians: Vec<String>,
+}
+
+/// Acknowledgement written by remote slaves for every
+/// [`SlavePacket::ExecuteBatch`].
+#[cw_serde]
+pub enum SlaveAck {
+    Result(BatchResult),
+    Error(String),
+}
+
+/// Packets sent by remote slaves to the host, acknowledged with a
+/// [`cosmwasm_std::StdAck`].
+#[cw_serde]
+pub enum HostPacket {
+    /// Approval of the social recovery by `guardian` on the slave chain.
+    ApproveRecovery { guardian: String, target: String },
+}
 
 #[cw_serde]
 pub enum MasterMsg {
-    UpdateOwner { old_owner: Addr, new_owner: Addr },
+    UpdateOwner { old_owner: Addr, new_owner: Addr, recovery: bool },
+    UpdateGuardians { guardians: Vec<Addr> },
+}
+
+#[cw_serde]
+pub enum MasterQueryMsg {
+    GetSlaveCodeId { chain: String },
+    GetChain { chain_id: String },
+    GetSlaveHost { slave: Addr },
+}
+
+#[cw_serde]
+pub struct GetSlaveCodeIdResponse {
+    pub code_id: Option<u64>,
+}
+
+#[cw_serde]
+pub struct SlaveHostResponse {
+    pub slave: Option<SlaveContract>,
+}
+
+/// Slave instantiated by the master for a host.
+#[cw_serde]
+pub struct SlaveContract {
+    pub host: Addr,
+    pub chain: String,
+    pub address: Addr,
+}
+
+#[cw_serde]
+pub struct ChainResponse {
+    pub chain: Option<ChainInfo>,
+}
+
+/// Chain in the registry of the master.
+#[cw_serde]
+pub struct ChainInfo {
+    pub chain_id: String,
+    pub bech32_prefix: String,
+    pub connection_id: Option<String>,
+    pub transfer_channel_id: Option<String>,
+    pub fee_denom: String,
+    pub slave_kind: ChainSlaveKind,
+}
+
+#[cw_serde]
+pub enum ChainSlaveKind {
+    Samechain,
+    WasmIbc,
+    Ica,
 }
@@ -0,0 +1,130 @@
+use cosmwasm_std::{
+    Addr, Coin, CosmosMsg, StdResult, Storage, Timestamp, WasmMsg,
+};
+
+use crate::error::ContractError;
+use crate::spending::outgoing_funds;
+use crate::state::{MsgKind, SessionKey, SESSION_KEYS};
+
+pub fn msg_kind(msg: &CosmosMsg) -> Option<MsgKind> {
+    match msg {
+        CosmosMsg::Bank(_) => Some(MsgKind::Bank),
+        CosmosMsg::Wasm(_) => Some(MsgKind::Wasm),
+        CosmosMsg::Staking(_) => Some(MsgKind::Staking),
+        CosmosMsg::Distribution(_) => Some(MsgKind::Distribution),
+        CosmosMsg::Ibc(_) => Some(MsgKind::Ibc),
+        CosmosMsg::Gov(_) => Some(MsgKind::Gov),
+        _ => None,
+    }
+}
+
+/// Contract a wasm message acts on, `None` for instantiation.
+fn wasm_target(msg: &WasmMsg) -> Option<&String> {
+    match msg {
+        WasmMsg::Execute { contract_addr, .. }
+        | WasmMsg::Migrate { contract_addr, .. }
+        | WasmMsg::UpdateAdmin { contract_addr, .. }
+        | WasmMsg::ClearAdmin { contract_addr } => Some(contract_addr),
+        _ => None,
+    }
+}
+
+fn contract_allowed(key: &SessionKey, msg: &CosmosMsg) -> bool {
+    if key.allowed_contracts.is_empty() {
+        return true;
+    }
+    let CosmosMsg::Wasm(msg) = msg else {
+        return true;
+    };
+    // Instantiation has no target to restrict by
+    wasm_target(msg).is_some_and(|target| {
+        key.allowed_contracts.iter().any(|addr| addr == target)
+    })
+}
+
+/// The slave takes calls to itself from the host as settings changes,
+/// like its escape hatch, which only the owner may make.
+fn calls_slave(msg: &CosmosMsg, slave: &Addr) -> bool {
+    let CosmosMsg::Wasm(msg) = msg else {
+        return false;
+    };
+    wasm_target(msg).is_some_and(|target| target == slave.as_str())
+}
+
+fn add_spent(spent: &mut Vec<Coin>, coin: Coin) -> StdResult<()> {
+    match spent.iter_mut().find(|c| c.denom == coin.denom) {
+        Some(existing) => {
+            existing.amount = existing.amount.checked_add(coin.amount)?
+        }
+        None => spent.push(coin),
+    }
+    Ok(())
+}
+
+/// Give back the cap used by a transaction whose execution failed. Keys
+/// revoked in the meantime are left alone.
+pub fn release(
+    storage: &mut dyn Storage,
+    key: &Addr,
+    funds: &[Coin],
+) -> StdResult<()> {
+    let Some(mut session) = SESSION_KEYS.may_load(storage, key)? else {
+        return Ok(());
+    };
+    for coin in funds {
+        if let Some(spent) =
+            session.spent.iter_mut().find(|c| c.denom == coin.denom)
+        {
+            spent.amount = spent.amount.saturating_sub(coin.amount);
+        }
+    }
+    SESSION_KEYS.save(storage, key, &session)
+}
+
+/// Check that session key `key` may execute `msg` on `slave` and account
+/// its spending against the key cap. Nothing is written on failure.
+pub fn use_key(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    key: &Addr,
+    slave: &Addr,
+    msg: &CosmosMsg,
+) -> Result<(), ContractError> {
+    let mut session = SESSION_KEYS
+        .may_load(storage, key)?
+        .ok_or(ContractError::Unauthorized {})?;
+    if now >= session.expires_at {
+        return Err(ContractError::SessionKeyExpired {});
+    }
+    let kind_allowed = msg_kind(msg).is_some_and(|kind| {
+        session.allowed_msgs.is_empty()
+            || session.allowed_msgs.contains(&kind)
+    });
+    if !kind_allowed
+        || !contract_allowed(&session, msg)
+        || calls_slave(msg, slave)
+    {
+        return Err(ContractError::SessionKeyForbidden {});
+    }
+
+    let funds =
+        outgoing_funds(msg).ok_or(ContractError::UncheckableMessage {})?;
+    for coin in funds {
+        add_spent(&mut session.spent, coin)?;
+    }
+    for coin in session.spent.iter() {
+        let limit = session
+            .spend_limit
+            .iter()
+            .find(|c| c.denom == coin.denom)
+            .map(|c| c.amount)
+            .unwrap_or_default();
+        if coin.amount > limit {
+            return Err(ContractError::SessionKeyLimitExceeded {
+                denom: coin.denom.clone(),
+            });
+        }
+    }
+    SESSION_KEYS.save(storage, key, &session)?;
+    Ok(())
+}
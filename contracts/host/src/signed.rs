@@ -0,0 +1,83 @@
+use cosmwasm_std::{from_json, Api, Binary, Env, Storage};
+use sha2::{Digest, Sha256};
+
+use crate::error::ContractError;
+use crate::msg::SignedPayload;
+use crate::state::{GuardianPubkey, State, LAST_NONCE};
+
+pub fn validate_pubkey(pubkey: &Binary) -> Result<(), ContractError> {
+    // Compressed or uncompressed SEC1 secp256k1 key
+    match pubkey.len() {
+        33 | 65 => Ok(()),
+        _ => Err(ContractError::InvalidPubkey {}),
+    }
+}
+
+pub fn validate_guardian_pubkey(
+    pubkey: &GuardianPubkey,
+) -> Result<(), ContractError> {
+    match pubkey {
+        GuardianPubkey::Secp256k1(key) => validate_pubkey(key),
+        GuardianPubkey::Ed25519(key) if key.len() == 32 => Ok(()),
+        GuardianPubkey::Ed25519(_) => Err(ContractError::InvalidPubkey {}),
+    }
+}
+
+/// Check a guardian signature over the sha256 of `payload`, for both key
+/// types alike.
+pub fn verify_guardian(
+    api: &dyn Api,
+    pubkey: &GuardianPubkey,
+    payload: &Binary,
+    signature: &Binary,
+) -> Result<(), ContractError> {
+    let hash = Sha256::digest(payload.as_slice());
+    let verified = match pubkey {
+        GuardianPubkey::Secp256k1(key) => {
+            api.secp256k1_verify(&hash, signature, key)
+        }
+        GuardianPubkey::Ed25519(key) => {
+            api.ed25519_verify(&hash, signature, key)
+        }
+    };
+    if !matches!(verified, Ok(true)) {
+        return Err(ContractError::InvalidSignature {});
+    }
+    Ok(())
+}
+
+/// Authenticate an owner-signed `payload` and burn its nonce along with
+/// every lower one.
+///
+/// The signature covers the sha256 of the raw payload bytes, so signers
+/// do not have to reproduce the contract JSON encoding.
+pub fn verify(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    env: &Env,
+    state: &State,
+    payload: &Binary,
+    signature: &Binary,
+) -> Result<SignedPayload, ContractError> {
+    let pubkey =
+        state.owner_pubkey.as_ref().ok_or(ContractError::MissingPubkey {})?;
+    let hash = Sha256::digest(payload.as_slice());
+    if !matches!(api.secp256k1_verify(&hash, signature, pubkey), Ok(true)) {
+        return Err(ContractError::InvalidSignature {});
+    }
+
+    let payload: SignedPayload = from_json(payload)?;
+    if payload.chain_id != env.block.chain_id
+        || payload.host != env.contract.address
+    {
+        return Err(ContractError::SignatureDomainMismatch {});
+    }
+    if env.block.time >= payload.expires_at {
+        return Err(ContractError::SignatureExpired {});
+    }
+    if payload.nonce <= LAST_NONCE.may_load(storage)?.unwrap_or_default() {
+        return Err(ContractError::NonceAlreadyUsed {});
+    }
+    LAST_NONCE.save(storage, &payload.nonce)?;
+    Ok(payload)
+}
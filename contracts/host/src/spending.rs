@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{
+    from_json, BankMsg, Coin, CosmosMsg, IbcMsg, Order, StdError, StdResult,
+    Storage, Timestamp, Uint128, WasmMsg,
+};
+use serde::Deserialize;
+
+use crate::error::ContractError;
+use crate::msg::SpendingAllowance;
+use crate::state::{SPENDINGS, SPENDING_LIMITS};
+
+/// Subset of CW20 execute messages that move tokens out of the wallet.
+/// Unknown fields are ignored on purpose, we only care about amounts.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Cw20ExecuteMsg {
+    Transfer { amount: Uint128 },
+    Send { amount: Uint128 },
+    Burn { amount: Uint128 },
+    IncreaseAllowance { amount: Uint128 },
+}
+
+impl Cw20ExecuteMsg {
+    fn amount(&self) -> Uint128 {
+        match self {
+            Self::Transfer { amount }
+            | Self::Send { amount }
+            | Self::Burn { amount }
+            | Self::IncreaseAllowance { amount } => *amount,
+        }
+    }
+}
+
+/// Funds leaving the wallet when `msg` is executed. CW20 tokens are
+/// reported with the token contract address as denom.
+///
+/// Returns `None` for opaque messages (stargate, custom) that cannot be
+/// inspected. A contract call without funds that is not a CW20 transfer
+/// counts as spending nothing, session keys restrict such calls through
+/// their contract allow list instead.
+pub fn outgoing_funds(msg: &CosmosMsg) -> Option<Vec<Coin>> {
+    match msg {
+        CosmosMsg::Bank(BankMsg::Send { amount, .. })
+        | CosmosMsg::Bank(BankMsg::Burn { amount }) => Some(amount.clone()),
+        CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }) => {
+            let mut spent = funds.clone();
+            if let Ok(cw20) = from_json::<Cw20ExecuteMsg>(msg) {
+                spent.push(Coin {
+                    denom: contract_addr.clone(),
+                    amount: cw20.amount(),
+                });
+            }
+            Some(spent)
+        }
+        CosmosMsg::Wasm(WasmMsg::Instantiate { funds, .. }) => {
+            Some(funds.clone())
+        }
+        CosmosMsg::Wasm(WasmMsg::Migrate { .. })
+        | CosmosMsg::Wasm(WasmMsg::UpdateAdmin { .. })
+        | CosmosMsg::Wasm(WasmMsg::ClearAdmin { .. }) => Some(vec![]),
+        CosmosMsg::Ibc(IbcMsg::Transfer { amount, .. }) => {
+            Some(vec![amount.clone()])
+        }
+        CosmosMsg::Ibc(_)
+        | CosmosMsg::Staking(_)
+        | CosmosMsg::Distribution(_)
+        | CosmosMsg::Gov(_) => Some(vec![]),
+        _ => None,
+    }
+}
+
+fn add_total(
+    totals: &mut BTreeMap<String, Uint128>,
+    coin: Coin,
+) -> StdResult<()> {
+    let total = totals.entry(coin.denom).or_default();
+    *total = total.checked_add(coin.amount)?;
+    Ok(())
+}
+
+fn total_by_denom(
+    msgs: &[CosmosMsg],
+) -> Result<BTreeMap<String, Uint128>, ContractError> {
+    let mut totals = BTreeMap::<String, Uint128>::new();
+    for msg in msgs {
+        let funds = outgoing_funds(msg)
+            .ok_or(ContractError::UncheckableMessage {})?;
+        for coin in funds {
+            add_total(&mut totals, coin)?;
+        }
+    }
+    Ok(totals)
+}
+
+fn has_limits(storage: &dyn Storage) -> bool {
+    SPENDING_LIMITS
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+}
+
+fn limits_for(
+    storage: &dyn Storage,
+    denom: &str,
+) -> StdResult<Vec<(u64, Uint128)>> {
+    SPENDING_LIMITS
+        .prefix(denom)
+        .range(storage, None, None, Order::Ascending)
+        .collect()
+}
+
+fn spent_since(
+    storage: &dyn Storage,
+    denom: &str,
+    since: u64,
+) -> StdResult<Uint128> {
+    SPENDINGS
+        .may_load(storage, denom)?
+        .unwrap_or_default()
+        .iter()
+        .filter(|(time, _)| time.seconds() > since)
+        .try_fold(Uint128::zero(), |total, (_, amount)| {
+            Ok(total.checked_add(*amount)?)
+        })
+}
+
+fn record(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    totals: BTreeMap<String, Uint128>,
+) -> StdResult<()> {
+    for (denom, amount) in totals {
+        let limits = limits_for(storage, &denom)?;
+        let Some(longest) = limits.iter().map(|(period, _)| *period).max()
+        else {
+            // Nothing to enforce, no need to keep the log either
+            continue;
+        };
+        let since = now.seconds().saturating_sub(longest);
+        let mut log =
+            SPENDINGS.may_load(storage, &denom)?.unwrap_or_default();
+        log.retain(|(time, _)| time.seconds() > since);
+        log.push((now, amount));
+        SPENDINGS.save(storage, &denom, &log)?;
+    }
+    Ok(())
+}
+
+/// Check `msgs` against every configured limit and, if all of them hold,
+/// record the spending. Nothing is written on failure.
+pub fn spend(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    msgs: &[CosmosMsg],
+) -> Result<(), ContractError> {
+    if !has_limits(storage) {
+        return Ok(());
+    }
+    let totals = total_by_denom(msgs)?;
+    for (denom, amount) in totals.iter() {
+        for (period, limit) in limits_for(storage, denom)? {
+            let since = now.seconds().saturating_sub(period);
+            let spent = spent_since(storage, denom, since)?;
+            if spent.checked_add(*amount).map_err(StdError::from)? > limit {
+                return Err(ContractError::SpendingLimitExceeded {
+                    denom: denom.clone(),
+                });
+            }
+        }
+    }
+    Ok(record(storage, now, totals)?)
+}
+
+/// Give back spending recorded at `time` by a transaction whose execution
+/// failed, `spent` being its totals by denom.
+pub fn release(
+    storage: &mut dyn Storage,
+    time: Timestamp,
+    spent: &[Coin],
+) -> StdResult<()> {
+    for coin in spent {
+        let Some(mut log) = SPENDINGS.may_load(storage, &coin.denom)? else {
+            continue;
+        };
+        let entry = (time, coin.amount);
+        if let Some(index) = log.iter().position(|e| *e == entry) {
+            log.remove(index);
+            SPENDINGS.save(storage, &coin.denom, &log)?;
+        }
+    }
+    Ok(())
+}
+
+/// Record spending of already approved `msgs` without enforcing limits.
+/// Opaque messages are skipped.
+pub fn spend_unchecked(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    msgs: &[CosmosMsg],
+) -> StdResult<()> {
+    let mut totals = BTreeMap::<String, Uint128>::new();
+    for coin in msgs.iter().filter_map(outgoing_funds).flatten() {
+        add_total(&mut totals, coin)?;
+    }
+    record(storage, now, totals)
+}
+
+pub fn allowances(
+    storage: &dyn Storage,
+    now: Timestamp,
+    denom: Option<String>,
+) -> StdResult<Vec<SpendingAllowance>> {
+    let limits: Vec<_> = match denom {
+        Some(denom) => limits_for(storage, &denom)?
+            .into_iter()
+            .map(|(period, limit)| ((denom.clone(), period), limit))
+            .collect(),
+        None => SPENDING_LIMITS
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?,
+    };
+    limits
+        .into_iter()
+        .map(|((denom, period), limit)| {
+            let since = now.seconds().saturating_sub(period);
+            let spent = spent_since(storage, &denom, since)?;
+            Ok(SpendingAllowance {
+                remaining: limit.saturating_sub(spent),
+                denom,
+                period,
+                limit,
+                spent,
+            })
+        })
+        .collect()
+}
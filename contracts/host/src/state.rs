@@ -1,7 +1,9 @@
-use cosmwasm_std::{Addr, StdError, StdResult, Storage};
-use cw_storage_plus::{Deque, Item};
+use cosmwasm_std::{
+    Addr, Binary, Coin, CosmosMsg, QuerierWrapper, Timestamp, Uint128,
+};
+use cw_storage_plus::{Deque, Item, Map};
 use schemars::JsonSchema;
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct State {
@@ -9,55 +11,255 @@ pub struct State {
     pub master: Addr,
     pub potential_owner: Option<Addr>, // Used during ownership transfer
     pub recovery_method: Option<String>, // Used during ownership transfer
-    pub recovery_pool: Vec<Addr>,
+    pub recovery_pool: Vec<RecoveryMember>,
     pub approval_pool: Vec<Addr>,
     pub recovery_approvals_needed: u32,
     pub transfer_ownership_approvals_needed: u32,
+    pub proposal_approvals_needed: u32,
+    pub limit_exceeded_action: LimitExceededAction,
+    pub recovery_initiator: Option<Addr>, // Used during ownership transfer
+    pub recovery_lifetime: u64,           // Seconds to collect approvals
+    pub recovery_expires_at: Option<Timestamp>, // Used during ownership transfer
+    pub recovery_delay: u64, // Seconds between recovery quorum and transfer
+    pub recovery_unlocks_at: Option<Timestamp>, // Set once quorum is reached
+    pub owner_pubkey: Option<Binary>, // secp256k1, for `ExecuteSigned`
+    pub recovery_nonce: u64, // Bumped when a recovery process ends
     pub chain: String,
 }
 
-pub const STATE: Item<State> = Item::new("state");
-pub const SLAVES: Deque<(String, Addr)> = Deque::new("slaves");
-pub const ACTIVE_RECOVERY: Deque<Addr> = Deque::new("ar");
+impl State {
+    /// Member voting as `voter`, see [`RecoveryMember::voter`].
+    pub fn recovery_member(&self, voter: &Addr) -> Option<&RecoveryMember> {
+        self.recovery_pool.iter().find(|m| m.voter() == *voter)
+    }
+
+    /// Total weight of current guardians among `voters`, owner excluded.
+    pub fn approvals_weight(&self, voters: &[Addr]) -> u32 {
+        self.recovery_pool
+            .iter()
+            .filter(|m| m.addr != self.owner && voters.contains(&m.voter()))
+            .map(|m| m.weight)
+            .sum()
+    }
+
+    pub fn required_approved(&self, voters: &[Addr]) -> bool {
+        self.recovery_pool
+            .iter()
+            .filter(|m| m.required)
+            .all(|m| voters.contains(&m.voter()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SlaveInfo {
+    pub addr: Addr,
+    pub kind: SlaveKind,
+    /// cw2 version reported by the slave when registered, if any.
+    pub code_version: Option<String>,
+    pub registered_at: Timestamp,
+}
+
+impl SlaveInfo {
+    pub fn new(querier: &QuerierWrapper, addr: Addr, now: Timestamp) -> Self {
+        let code_version = cw2::query_contract_info(querier, &addr)
+            .ok()
+            .map(|info| info.version);
+        Self {
+            addr,
+            kind: SlaveKind::Local,
+            code_version,
+            registered_at: now,
+        }
+    }
+}
 
-pub fn get_key<'a, K, V>(
-    deque: &Deque<(K, V)>,
-    store: &dyn Storage,
-    key: &K,
-) -> StdResult<V>
-where
-    (K, V): Serialize + DeserializeOwned,
-    K: Eq,
-{
-    deque
-        .iter(store)?
-        .filter_map(|el| el.ok())
-        .filter(|(k, _)| k == key)
-        .next()
-        .map_or(
-            Err(StdError::NotFound { kind: "Key not found".to_string() }),
-            |p| Ok(p.1),
-        )
-}
-
-pub fn set_key<'a, K, V>(
-    deque: &Deque<(K, V)>,
-    store: &mut dyn Storage,
-    key: K,
-    value: V,
-    validate_unique: bool,
-) -> StdResult<()>
-where
-    (K, V): Serialize + DeserializeOwned,
-    K: Eq,
-{
-    if validate_unique {
-        if let Ok(_) = get_key(deque, store, &key) {
-            return Err(StdError::GenericErr {
-                msg: "Key already set.".to_string(),
-            });
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SlaveKind {
+    /// Contract on the host chain, executed directly.
+    Local,
+    /// Slave on a remote chain, reached with packets over `channel_id`.
+    Ibc { channel_id: String },
+    /// ICS-27 interchain account, controlled through the controller module
+    /// of the host chain over `connection_id`.
+    Ica { connection_id: String },
+}
+
+impl SlaveKind {
+    pub fn channel_id(&self) -> Option<&str> {
+        match self {
+            // The controller module owns the channel of an interchain account
+            Self::Local | Self::Ica { .. } => None,
+            Self::Ibc { channel_id } => Some(channel_id),
         }
     }
-    deque.push_back(store, &(key, value))?;
-    Ok(())
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RecoveryMember {
+    pub addr: Addr,
+    pub weight: u32,
+    /// Quorum is never reached without approval of this member.
+    pub required: bool,
+    /// Allows approving recovery with an off-chain signature.
+    pub pubkey: Option<GuardianPubkey>,
+    /// Chain of a guardian approving through the remote slave there,
+    /// `addr` is then an address on that chain.
+    pub chain: Option<String>,
+}
+
+impl RecoveryMember {
+    /// Identity the member votes with: its address, or `chain/addr` for
+    /// remote guardians, which can never collide with a local address.
+    pub fn voter(&self) -> Addr {
+        match &self.chain {
+            Some(chain) => remote_voter(chain, self.addr.as_str()),
+            None => self.addr.clone(),
+        }
+    }
+}
+
+/// Voter identity of guardian `addr` on a remote `chain`.
+pub fn remote_voter(chain: &str, addr: &str) -> Addr {
+    Addr::unchecked(format!("{chain}/{addr}"))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardianPubkey {
+    Secp256k1(Binary),
+    Ed25519(Binary),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitExceededAction {
+    /// Fail the transaction.
+    Reject,
+    /// Turn the transaction into a proposal for the approval pool.
+    Escalate,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MsgKind {
+    Bank,
+    Wasm,
+    Staking,
+    Distribution,
+    Ibc,
+    Gov,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SessionKey {
+    pub expires_at: Timestamp,
+    /// Total amount the key may spend, denoms not listed cannot be spent.
+    pub spend_limit: Vec<Coin>,
+    pub spent: Vec<Coin>,
+    /// Any kind is allowed if empty.
+    pub allowed_msgs: Vec<MsgKind>,
+    /// Any contract is allowed if empty.
+    pub allowed_contracts: Vec<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// Host side effects of a same-chain batch, undone if the slave fails.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Reservation {
+    /// Session key whose cap was charged.
+    pub session_key: Option<Addr>,
+    /// Attached funds, refunded to the sender.
+    pub funds: Vec<Coin>,
+}
+
+/// Executed wallet transaction, recorded for the UI history view.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Transaction {
+    pub id: u64,
+    pub sender: Addr,
+    pub height: u64,
+    pub time: Timestamp,
+    /// One short description per message, see `history::summarize`.
+    pub msgs: Vec<String>,
+    /// Funds leaving the wallet, opaque messages are not counted.
+    pub funds: Vec<Coin>,
+    pub status: TxStatus,
+    /// Indices of messages that failed in a best-effort batch.
+    pub failed_msgs: Vec<u32>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Open,
+    Executed,
+    Cancelled,
+    /// Never stored, reported for open proposals past their expiration.
+    Expired,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: Addr,
+    pub msgs: Vec<CosmosMsg>,
+    pub approvals: Vec<Addr>,
+    pub status: ProposalStatus,
+    pub expires_at: Timestamp,
+}
+
+pub const STATE: Item<State> = Item::new("state");
+/// chain -> slave controlling the wallet on it
+pub const SLAVES: Map<&str, SlaveInfo> = Map::new("slave_registry");
+pub const ACTIVE_RECOVERY: Deque<Addr> = Deque::new("ar");
+/// guardian -> recovery nonce of the process it retracted its approval from
+pub const RETRACTED_APPROVALS: Map<&Addr, u64> =
+    Map::new("retracted_approvals");
+pub const SESSION_KEYS: Map<&Addr, SessionKey> = Map::new("session_keys");
+/// Remote slaves allowed to connect, by their port id.
+pub const EXPECTED_SLAVES: Map<&str, String> = Map::new("expected_slaves");
+/// chain -> connection of an interchain account being opened
+pub const EXPECTED_ICAS: Map<&str, String> = Map::new("expected_icas");
+/// Version of the config mirrored by remote slaves, bumped on changes.
+pub const CONFIG_VERSION: Item<u64> = Item::new("config_version");
+/// chain -> latest config version acknowledged by its remote slave
+pub const SYNCED_CONFIGS: Map<&str, u64> = Map::new("synced_configs");
+pub const PROPOSALS: Map<u64, Proposal> = Map::new("proposals");
+/// Highest nonce accepted from the owner key, signed payloads must use a
+/// greater one. Keeps replay protection at a single entry.
+pub const LAST_NONCE: Item<u64> = Item::new("last_nonce");
+pub const TRANSACTIONS: Map<u64, Transaction> = Map::new("transactions");
+pub const TX_COUNT: Item<u64> = Item::new("tx_count");
+/// tx id -> reservation of a pending same-chain batch
+pub const RESERVATIONS: Map<u64, Reservation> = Map::new("reservations");
+pub const PENDING_TXS: Deque<u64> = Deque::new("pending_txs");
+pub const PROPOSAL_COUNT: Item<u64> = Item::new("proposal_count");
+/// (denom, period in seconds) -> maximal amount spent during any period
+pub const SPENDING_LIMITS: Map<(&str, u64), Uint128> =
+    Map::new("spending_limits");
+/// Raised or removed limit waiting for its timelock.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingLimit {
+    /// `None` removes the limit.
+    pub amount: Option<Uint128>,
+    pub effective_at: Timestamp,
+}
+
+/// (denom, period in seconds) -> loosened limit, see `ApplyLimitChanges`
+pub const PENDING_LIMITS: Map<(&str, u64), PendingLimit> =
+    Map::new("pending_limits");
+/// When the switch to `LimitExceededAction::Escalate` takes effect
+pub const PENDING_ESCALATION: Item<Timestamp> =
+    Item::new("pending_escalation");
+/// denom -> spendings still inside the longest window of this denom
+pub const SPENDINGS: Map<&str, Vec<(Timestamp, Uint128)>> =
+    Map::new("spendings");
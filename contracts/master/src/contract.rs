@@ -1,22 +1,38 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo,
-    Reply, Response, StdResult, SubMsg, SubMsgResult, WasmMsg,
+    entry_point, instantiate2_address, to_json_binary, Addr, Binary, Deps,
+    DepsMut, Env, MessageInfo, Order, Reply, Response, StdError, StdResult,
+    Storage, SubMsg, SubMsgResult, WasmMsg,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::Bound;
+use cw_utils::parse_instantiate_response_data;
 use itertools::Itertools;
+use semver::Version;
+use sha2::{Digest, Sha256};
 
 use crate::error::ContractError;
 use crate::msg::{
-    ExecuteMsg, HostInstantiateMsg, InstantiateMsg, QueryMsg,
-    SlaveInstantiateMsg,
+    ExecuteMsg, HostGetSlaveResponse, HostInstantiateMsg, HostMigrateMsg,
+    HostQueryMsg, HostRecoveryMember, HostRecoveryPoolResponse,
+    InstantiateMsg, MigrateMsg, QueryMsg, RecoveryMember,
+    SlaveInstantiateMsg, SlaveMigrateMsg,
+};
+use crate::state::{
+    guardians, slave_contracts, wallets, ChainInfo, CodeId, Guardian,
+    PendingHost, SlaveContract, SlaveKind, State, Wallet, ADMIN, CHAINS,
+    DEFAULT_WALLETS, DEFAULT_WALLET_NAME, DEPRECATED_CODE_IDS,
+    LEGACY_WALLETS, PENDING_ADMIN, PENDING_HOST, SLAVES, SLAVE_COUNTS, STATE,
+    STATS,
 };
-use crate::state::{State, SLAVES, STATE, WALLETS};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:master";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
 #[repr(u8)]
 enum ReplyKind {
     ReplyCreateHost = 1,
@@ -37,13 +53,18 @@ impl std::convert::TryFrom<u64> for ReplyKind {
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     let state =
         State { host_code_id: msg.host_code_id, host_chain: msg.host_chain };
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender,
+    };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
+    ADMIN.save(deps.storage, &admin)?;
     for (k, v) in msg.slave_code_ids.iter() {
         SLAVES.save(deps.storage, k.clone(), v)?;
     }
@@ -57,13 +78,196 @@ pub fn instantiate(
     Ok(Response::new()
         .add_attribute("method", "instantiate")
         .add_attribute("host", msg.host_code_id.to_string())
-        .add_attribute("slaves", format!("{{{}}}", slaves_repr)))
+        .add_attribute("slaves", format!("{{{}}}", slaves_repr))
+        .add_attribute("admin", admin))
+}
+
+/// Instantiate2 salt of the contract created for `seed`: hosts are salted
+/// with their owner and name, slaves with their host and a counter.
+fn salt(seed: &str) -> Binary {
+    Sha256::digest(seed.as_bytes()).to_vec().into()
+}
+
+fn host_salt(owner: &Addr, name: &str) -> Binary {
+    salt(&format!("{owner}/{name}"))
+}
+
+fn wallet_name(name: Option<String>) -> Result<String, ContractError> {
+    let name = name.unwrap_or_else(|| DEFAULT_WALLET_NAME.to_string());
+    let valid = (1..=32).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(ContractError::InvalidWalletName {});
+    }
+    Ok(name)
+}
+
+/// Named wallet of `owner`, the default one if `name` is not given.
+fn load_wallet(
+    storage: &dyn Storage,
+    owner: &Addr,
+    name: Option<String>,
+) -> StdResult<Option<Wallet>> {
+    let name = match name {
+        Some(name) => name,
+        None => match DEFAULT_WALLETS.may_load(storage, owner)? {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+    };
+    wallets().may_load(storage, (owner, &name))
+}
+
+fn owner_wallets(
+    storage: &dyn Storage,
+    owner: &Addr,
+) -> StdResult<Vec<Wallet>> {
+    wallets()
+        .prefix(owner)
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, wallet)| wallet))
+        .collect()
+}
+
+fn wallet_by_host(
+    storage: &dyn Storage,
+    host: &Addr,
+) -> StdResult<Option<Wallet>> {
+    let item = wallets().idx.host.item(storage, host.clone())?;
+    Ok(item.map(|(_, wallet)| wallet))
+}
+
+/// Add the wallet to its owner, the first one becomes default.
+fn save_wallet(storage: &mut dyn Storage, wallet: &Wallet) -> StdResult<()> {
+    wallets().save(storage, (&wallet.owner, &wallet.name), wallet)?;
+    if !DEFAULT_WALLETS.has(storage, &wallet.owner) {
+        DEFAULT_WALLETS.save(storage, &wallet.owner, &wallet.name)?;
+    }
+    Ok(())
+}
+
+/// Replace the recovery pool recorded for `host`.
+fn save_guardians(
+    storage: &mut dyn Storage,
+    host: &Addr,
+    members: &[Addr],
+) -> StdResult<()> {
+    let previous = guardians()
+        .prefix(host)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for guardian in previous {
+        guardians().remove(storage, (host, &guardian))?;
+    }
+    for guardian in members {
+        let entry =
+            Guardian { host: host.clone(), guardian: guardian.clone() };
+        guardians().save(storage, (host, guardian), &entry)?;
+    }
+    Ok(())
+}
+
+/// Drop the wallet `name` of `owner`, another one becomes default.
+fn remove_wallet(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    name: &str,
+) -> StdResult<()> {
+    wallets().remove(storage, (owner, name))?;
+    if DEFAULT_WALLETS.may_load(storage, owner)?.as_deref() == Some(name) {
+        match owner_wallets(storage, owner)?.into_iter().next() {
+            Some(next) => DEFAULT_WALLETS.save(storage, owner, &next.name)?,
+            None => DEFAULT_WALLETS.remove(storage, owner),
+        }
+    }
+    Ok(())
+}
+
+/// Name `owner` can take `wallet` under, suffixed with a hash of its host
+/// when the owner already has a wallet with the same name.
+fn free_wallet_name(
+    storage: &dyn Storage,
+    owner: &Addr,
+    wallet: &Wallet,
+) -> StdResult<String> {
+    if !wallets().has(storage, (owner, &wallet.name)) {
+        return Ok(wallet.name.clone());
+    }
+    let hash = format!("{:x}", Sha256::digest(wallet.host.as_bytes()));
+    // Longer suffixes are only needed if the owner picked such names
+    for len in 8..31 {
+        let keep = wallet.name.len().min(31 - len);
+        let name = format!("{}-{}", &wallet.name[..keep], &hash[..len]);
+        if !wallets().has(storage, (owner, &name)) {
+            return Ok(name);
+        }
+    }
+    Ok(hash[..32].to_string())
+}
+
+/// Address of `code_id` instantiated by the master with `salt`.
+fn predict_address(
+    deps: Deps,
+    env: &Env,
+    code_id: CodeId,
+    salt: &Binary,
+) -> StdResult<Addr> {
+    let checksum = deps.querier.query_wasm_code_info(code_id)?.checksum;
+    let creator =
+        deps.api.addr_canonicalize(env.contract.address.as_str())?;
+    let addr = instantiate2_address(checksum.as_slice(), &creator, salt)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+    deps.api.addr_humanize(&addr)
+}
+
+fn require_admin(
+    storage: &dyn Storage,
+    info: &MessageInfo,
+) -> Result<(), ContractError> {
+    if ADMIN.load(storage)? != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn require_active_code(
+    storage: &dyn Storage,
+    code_id: CodeId,
+) -> Result<(), ContractError> {
+    if DEPRECATED_CODE_IDS.has(storage, code_id) {
+        return Err(ContractError::CodeIdDeprecated(code_id));
+    }
+    Ok(())
+}
+
+fn validate_chain(
+    state: &State,
+    chain: &ChainInfo,
+) -> Result<(), ContractError> {
+    let invalid =
+        |reason: &str| Err(ContractError::InvalidChain(reason.into()));
+    if chain.chain_id.is_empty()
+        || chain.bech32_prefix.is_empty()
+        || chain.fee_denom.is_empty()
+    {
+        return invalid("chain id, prefix and fee denom are required");
+    }
+    let is_host_chain = chain.chain_id == state.host_chain;
+    if is_host_chain != (chain.slave_kind == SlaveKind::Samechain) {
+        return invalid("only the host chain has same-chain slaves");
+    }
+    if !is_host_chain && chain.connection_id.is_none() {
+        return invalid("remote chains need an IBC connection");
+    }
+    Ok(())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -75,38 +279,110 @@ pub fn execute(
             approval_pool,
             recovery_approvals_needed,
             transfer_ownership_approvals_needed,
+            proposal_approvals_needed,
+            recovery_delay,
+            recovery_lifetime,
+            owner_pubkey,
+            name,
         } => {
+            let name = wallet_name(name)?;
+            if wallets().has(deps.storage, (&info.sender, &name)) {
+                return Err(ContractError::WalletExists {});
+            }
+            let salt = host_salt(&info.sender, &name);
+            let pending = PendingHost {
+                owner: info.sender.clone(),
+                name: name.clone(),
+                address: predict_address(
+                    deps.as_ref(),
+                    &env,
+                    state.host_code_id,
+                    &salt,
+                )?,
+                guardians: recovery_pool
+                    .iter()
+                    .map(RecoveryMember::voter)
+                    .collect(),
+            };
+            PENDING_HOST.save(deps.storage, &pending)?;
             let submsg = SubMsg::reply_on_success(
-                WasmMsg::Instantiate {
-                    admin: None,
+                WasmMsg::Instantiate2 {
+                    admin: Some(env.contract.address.to_string()),
                     code_id: state.host_code_id,
-                    msg: to_binary(&HostInstantiateMsg {
+                    msg: to_json_binary(&HostInstantiateMsg {
                         recovery_pool,
                         recovery_approvals_needed,
                         approval_pool,
                         transfer_ownership_approvals_needed,
+                        proposal_approvals_needed,
+                        recovery_delay,
+                        recovery_lifetime,
+                        owner_pubkey,
                         owner: info.sender.clone(),
                         chain: state.host_chain,
                     })?,
                     funds: vec![],
-                    label: info.sender.to_string(),
+                    label: format!("{}/{name}", info.sender),
+                    salt,
                 },
                 ReplyKind::ReplyCreateHost as u64,
             );
             Ok(Response::new()
                 .add_attribute("contract", "master")
                 .add_attribute("method", "create_host")
+                .add_attribute("host_address", pending.address)
                 .add_submessage(submsg))
         }
-        ExecuteMsg::CreateSlave { host_address, slave_chain } => {
-            if slave_chain == state.host_chain {
-                let msg = WasmMsg::Instantiate {
-                    admin: None,
-                    code_id: SLAVES
-                        .load(deps.storage, slave_chain.clone())?,
-                    msg: to_binary(&SlaveInstantiateMsg {
+        ExecuteMsg::CreateSlave {
+            host_address,
+            slave_chain,
+            escape_hatch,
+        } => {
+            // Slaves are bound to the host, only its owner may add them
+            let wallet = wallet_by_host(deps.storage, &host_address)?;
+            if wallet.map(|w| w.owner) != Some(info.sender.clone()) {
+                return Err(ContractError::Unauthorized {});
+            }
+            let chain = CHAINS
+                .may_load(deps.storage, &slave_chain)?
+                .ok_or(ContractError::ChainNotFound {})?;
+            if chain.slave_kind == SlaveKind::Samechain {
+                let code_id =
+                    SLAVES.load(deps.storage, slave_chain.clone())?;
+                require_active_code(deps.storage, code_id)?;
+                // Replaced slaves keep their address, never reuse it
+                let count = SLAVE_COUNTS
+                    .may_load(deps.storage, &host_address)?
+                    .unwrap_or_default();
+                SLAVE_COUNTS.save(
+                    deps.storage,
+                    &host_address,
+                    &(count + 1),
+                )?;
+                let salt = salt(&format!("{host_address}/{count}"));
+                let slave = SlaveContract {
+                    host: host_address.clone(),
+                    chain: slave_chain.clone(),
+                    address: predict_address(
+                        deps.as_ref(),
+                        &env,
+                        code_id,
+                        &salt,
+                    )?,
+                };
+                slave_contracts().save(
+                    deps.storage,
+                    (&slave.host, &slave.chain),
+                    &slave,
+                )?;
+                let msg = WasmMsg::Instantiate2 {
+                    admin: Some(env.contract.address.to_string()),
+                    code_id,
+                    salt,
+                    msg: to_json_binary(&SlaveInstantiateMsg {
                         owner: host_address,
                         chain: slave_chain,
+                        escape_hatch,
                     })?,
                     funds: vec![],
                     label: info.sender.to_string(),
@@ -114,42 +390,499 @@ pub fn execute(
                 Ok(Response::new()
                     .add_attribute("contract", "master")
                     .add_attribute("method", "add_slave")
+                    .add_attribute("slave_address", slave.address)
                     .add_message(msg))
+            } else if chain.slave_kind == SlaveKind::Ica {
+                Err(ContractError::NotImplemented(
+                    "Interchain accounts are registered by the host"
+                        .to_string(),
+                ))
             } else {
-                Err(ContractError::NotImplemented("No IBC yet".to_string()))
+                // Cannot instantiate on other chains, remote slaves are
+                // deployed there and connect over IBC once the host owner
+                // allows them with `AllowRemoteSlave`
+                Err(ContractError::NotImplemented(
+                    "Remote slaves must be deployed on their chain"
+                        .to_string(),
+                ))
             }
         }
-        ExecuteMsg::UpdateOwner { old_owner, new_owner } => {
-            let wallet = WALLETS.load(deps.storage, old_owner.clone())?;
-            if wallet != info.sender {
-                Err(ContractError::Unauthorized {})
-            } else {
-                WALLETS.remove(deps.storage, old_owner);
-                WALLETS.save(deps.storage, new_owner, &wallet)?;
-                Ok(Response::new()
-                    .add_attribute("contract", "master")
-                    .add_attribute("method", "update_owner"))
+        ExecuteMsg::UpdateOwner { old_owner, new_owner, recovery } => {
+            // Sent by the host, which only moves itself
+            let wallet = wallet_by_host(deps.storage, &info.sender)?
+                .filter(|wallet| wallet.owner == old_owner)
+                .ok_or(ContractError::Unauthorized {})?;
+            remove_wallet(deps.storage, &old_owner, &wallet.name)?;
+            // Ownership transfer must not fail, fall back to a unique name
+            let name = free_wallet_name(deps.storage, &new_owner, &wallet)?;
+            let wallet = Wallet { owner: new_owner, name, ..wallet };
+            save_wallet(deps.storage, &wallet)?;
+            // Reported by the host, see `Stats::recoveries_completed`
+            if recovery {
+                let mut stats =
+                    STATS.may_load(deps.storage)?.unwrap_or_default();
+                stats.recoveries_completed += 1;
+                STATS.save(deps.storage, &stats)?;
             }
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "update_owner")
+                .add_attribute("name", wallet.name))
+        }
+        ExecuteMsg::UpdateGuardians { guardians } => {
+            if wallet_by_host(deps.storage, &info.sender)?.is_none() {
+                return Err(ContractError::Unauthorized {});
+            }
+            save_guardians(deps.storage, &info.sender, &guardians)?;
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "update_guardians")
+                .add_attribute("host", info.sender))
+        }
+        ExecuteMsg::SetDefaultWallet { name } => {
+            if !wallets().has(deps.storage, (&info.sender, &name)) {
+                return Err(ContractError::WalletNotFound {});
+            }
+            DEFAULT_WALLETS.save(deps.storage, &info.sender, &name)?;
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "set_default_wallet")
+                .add_attribute("name", name))
+        }
+        ExecuteMsg::MigrateWallet { name } => {
+            let host = load_wallet(deps.storage, &info.sender, name)?
+                .ok_or(ContractError::Unauthorized {})?
+                .host;
+            require_active_code(deps.storage, state.host_code_id)?;
+            let mut msgs = vec![WasmMsg::Migrate {
+                contract_addr: host.to_string(),
+                new_code_id: state.host_code_id,
+                msg: to_json_binary(&HostMigrateMsg {})?,
+            }];
+            // Slaves on other chains are not administered from here
+            let res: HostGetSlaveResponse = deps.querier.query_wasm_smart(
+                &host,
+                &HostQueryMsg::GetSlave { chain: state.host_chain.clone() },
+            )?;
+            if let Some(slave) = res.slave {
+                let code_id =
+                    SLAVES.load(deps.storage, state.host_chain.clone())?;
+                require_active_code(deps.storage, code_id)?;
+                msgs.push(WasmMsg::Migrate {
+                    contract_addr: slave.to_string(),
+                    new_code_id: code_id,
+                    msg: to_json_binary(&SlaveMigrateMsg {})?,
+                });
+            }
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "migrate_wallet")
+                .add_attribute("host", host)
+                .add_messages(msgs))
+        }
+        ExecuteMsg::RegisterChain { chain } => {
+            require_admin(deps.storage, &info)?;
+            validate_chain(&state, &chain)?;
+            CHAINS.save(deps.storage, &chain.chain_id, &chain)?;
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "register_chain")
+                .add_attribute("chain_id", chain.chain_id))
         }
+        ExecuteMsg::RemoveChain { chain_id } => {
+            require_admin(deps.storage, &info)?;
+            if !CHAINS.has(deps.storage, &chain_id) {
+                return Err(ContractError::ChainNotFound {});
+            }
+            CHAINS.remove(deps.storage, &chain_id);
+            SLAVES.remove(deps.storage, chain_id.clone());
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "remove_chain")
+                .add_attribute("chain_id", chain_id))
+        }
+        ExecuteMsg::SetHostCodeId { code_id } => {
+            require_admin(deps.storage, &info)?;
+            require_active_code(deps.storage, code_id)?;
+            let previous = state.host_code_id;
+            STATE.save(
+                deps.storage,
+                &State { host_code_id: code_id, ..state },
+            )?;
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "set_host_code_id")
+                .add_attribute("previous", previous.to_string())
+                .add_attribute("code_id", code_id.to_string()))
+        }
+        ExecuteMsg::SetSlaveCodeId { chain, code_id } => {
+            require_admin(deps.storage, &info)?;
+            require_active_code(deps.storage, code_id)?;
+            if !CHAINS.has(deps.storage, &chain) {
+                return Err(ContractError::ChainNotFound {});
+            }
+            let previous = SLAVES.may_load(deps.storage, chain.clone())?;
+            SLAVES.save(deps.storage, chain.clone(), &code_id)?;
+            let response = Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "set_slave_code_id")
+                .add_attribute("chain", chain)
+                .add_attribute("code_id", code_id.to_string());
+            match previous {
+                Some(previous) => {
+                    Ok(response
+                        .add_attribute("previous", previous.to_string()))
+                }
+                None => Ok(response),
+            }
+        }
+        ExecuteMsg::DeprecateCodeId { code_id } => {
+            require_admin(deps.storage, &info)?;
+            // Replace the code first, wallets must always have one to use
+            let in_use = state.host_code_id == code_id
+                || SLAVES
+                    .range(deps.storage, None, None, Order::Ascending)
+                    .any(|item| matches!(item, Ok((_, id)) if id == code_id));
+            if in_use {
+                return Err(ContractError::CodeIdInUse(code_id));
+            }
+            DEPRECATED_CODE_IDS.save(deps.storage, code_id, &true)?;
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "deprecate_code_id")
+                .add_attribute("code_id", code_id.to_string()))
+        }
+        ExecuteMsg::ProposeAdmin { admin } => {
+            require_admin(deps.storage, &info)?;
+            let admin = deps.api.addr_validate(&admin)?;
+            PENDING_ADMIN.save(deps.storage, &admin)?;
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "propose_admin")
+                .add_attribute("pending_admin", admin))
+        }
+        ExecuteMsg::AcceptAdmin {} => {
+            let pending = PENDING_ADMIN
+                .may_load(deps.storage)?
+                .ok_or(ContractError::NoPendingAdmin {})?;
+            if pending != info.sender {
+                return Err(ContractError::Unauthorized {});
+            }
+            let previous = ADMIN.load(deps.storage)?;
+            ADMIN.save(deps.storage, &pending)?;
+            PENDING_ADMIN.remove(deps.storage);
+            Ok(Response::new()
+                .add_attribute("contract", "master")
+                .add_attribute("method", "accept_admin")
+                .add_attribute("previous", previous)
+                .add_attribute("admin", pending))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    deps: DepsMut,
+    _env: Env,
+    msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+    let parse = |version: &str| {
+        Version::parse(version)
+            .map_err(|e| ContractError::InvalidVersion(e.to_string()))
+    };
+    if parse(&stored.version)? > parse(CONTRACT_VERSION)? {
+        return Err(ContractError::CannotDowngrade {
+            previous_version: stored.version,
+        });
+    }
+
+    if let Some(host_code_id) = msg.host_code_id {
+        require_active_code(deps.storage, host_code_id)?;
+        STATE.update(deps.storage, |mut state| {
+            state.host_code_id = host_code_id;
+            StdResult::Ok(state)
+        })?;
+    }
+    for (chain, code_id) in msg.slave_code_ids.unwrap_or_default() {
+        require_active_code(deps.storage, code_id)?;
+        SLAVES.save(deps.storage, chain, &code_id)?;
+    }
+    match msg.admin {
+        Some(admin) => {
+            ADMIN.save(deps.storage, &deps.api.addr_validate(&admin)?)?
+        }
+        // Versions before 0.3.0 had no admin
+        None if !ADMIN.exists(deps.storage) => {
+            return Err(ContractError::AdminRequired {})
+        }
+        None => {}
     }
+    // Versions before 0.4.0 kept a single unnamed wallet per owner
+    let mut unindexed_hosts = 0u64;
+    if parse(&stored.version)? < parse("0.4.0")? {
+        let legacy: Vec<(Addr, Addr)> = LEGACY_WALLETS
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        let mut stats = STATS.may_load(deps.storage)?.unwrap_or_default();
+        stats.wallets_created += legacy.len() as u64;
+        STATS.save(deps.storage, &stats)?;
+        for (owner, host) in legacy {
+            // These hosts only report their guardians when the pool
+            // changes, a host that cannot be queried is indexed then
+            let pool: StdResult<HostRecoveryPoolResponse> = deps
+                .querier
+                .query_wasm_smart(&host, &HostQueryMsg::GetRecoveryPool {});
+            match pool {
+                Ok(pool) => {
+                    let members: Vec<Addr> = pool
+                        .members
+                        .iter()
+                        .map(HostRecoveryMember::voter)
+                        .collect();
+                    save_guardians(deps.storage, &host, &members)?;
+                }
+                Err(_) => unindexed_hosts += 1,
+            }
+            let name = DEFAULT_WALLET_NAME.to_string();
+            save_wallet(
+                deps.storage,
+                &Wallet { owner: owner.clone(), name, host },
+            )?;
+            LEGACY_WALLETS.remove(deps.storage, owner);
+        }
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("previous_version", stored.version)
+        .add_attribute("version", CONTRACT_VERSION)
+        .add_attribute("unindexed_hosts", unindexed_hosts.to_string()))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::GetHostContract { owner } => {
-            to_binary(&query::get_host(deps, owner)?)
+        QueryMsg::GetHostContract { owner, name } => {
+            to_json_binary(&query::get_host(deps, owner, name)?)
+        }
+        QueryMsg::ListWallets { owner } => {
+            to_json_binary(&query::list_wallets(deps, owner)?)
+        }
+        QueryMsg::ListAllWallets { start_after, limit } => to_json_binary(
+            &query::list_all_wallets(deps, start_after, limit)?,
+        ),
+        QueryMsg::GetWalletByHost { host } => {
+            to_json_binary(&query::get_wallet_by_host(deps, host)?)
+        }
+        QueryMsg::GetSlaveHost { slave } => {
+            to_json_binary(&query::get_slave_host(deps, slave)?)
+        }
+        QueryMsg::ListGuardedWallets { guardian, start_after, limit } => {
+            to_json_binary(&query::list_guarded_wallets(
+                deps,
+                guardian,
+                start_after,
+                limit,
+            )?)
+        }
+        QueryMsg::GetStats {} => to_json_binary(&query::get_stats(deps)?),
+        QueryMsg::PredictHostAddress { owner, name } => to_json_binary(
+            &query::predict_host_address(deps, env, owner, name)?,
+        ),
+        QueryMsg::GetSlaveCodeId { chain } => {
+            to_json_binary(&query::get_slave_code_id(deps, chain)?)
+        }
+        QueryMsg::GetAdmin {} => to_json_binary(&query::get_admin(deps)?),
+        QueryMsg::GetCodeIds {} => {
+            to_json_binary(&query::get_code_ids(deps)?)
+        }
+        QueryMsg::GetChain { chain_id } => {
+            to_json_binary(&query::get_chain(deps, chain_id)?)
+        }
+        QueryMsg::ListChains { start_after, limit } => {
+            to_json_binary(&query::list_chains(deps, start_after, limit)?)
         }
     }
 }
 
 pub mod query {
     use super::*;
-    use crate::msg::GetHostResponse;
+    use crate::msg::{
+        AdminResponse, ChainResponse, CodeIdsResponse, GetHostResponse,
+        GetSlaveCodeIdResponse, ListChainsResponse, ListWalletsResponse,
+        PredictHostAddressResponse, SlaveHostResponse, WalletByHostResponse,
+        WalletResponse, WalletsResponse,
+    };
+    use crate::state::Stats;
+
+    pub fn get_host(
+        deps: Deps,
+        owner: Addr,
+        name: Option<String>,
+    ) -> StdResult<GetHostResponse> {
+        let wallet = load_wallet(deps.storage, &owner, name)?
+            .ok_or_else(|| StdError::not_found("Wallet"))?;
+        Ok(GetHostResponse { host: wallet.host })
+    }
+
+    pub fn list_wallets(
+        deps: Deps,
+        owner: Addr,
+    ) -> StdResult<ListWalletsResponse> {
+        let wallets = owner_wallets(deps.storage, &owner)?
+            .into_iter()
+            .map(|Wallet { name, host, .. }| WalletResponse { name, host })
+            .collect();
+        Ok(ListWalletsResponse {
+            default: DEFAULT_WALLETS.may_load(deps.storage, &owner)?,
+            wallets,
+        })
+    }
+
+    pub fn list_all_wallets(
+        deps: Deps,
+        start_after: Option<(Addr, String)>,
+        limit: Option<u32>,
+    ) -> StdResult<WalletsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after
+            .as_ref()
+            .map(|(owner, name)| Bound::exclusive((owner, name.as_str())));
+        let wallets = wallets()
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, wallet)| wallet))
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(WalletsResponse { wallets })
+    }
+
+    pub fn get_wallet_by_host(
+        deps: Deps,
+        host: Addr,
+    ) -> StdResult<WalletByHostResponse> {
+        Ok(WalletByHostResponse {
+            wallet: wallet_by_host(deps.storage, &host)?,
+        })
+    }
+
+    pub fn get_slave_host(
+        deps: Deps,
+        slave: Addr,
+    ) -> StdResult<SlaveHostResponse> {
+        let item = slave_contracts().idx.address.item(deps.storage, slave)?;
+        Ok(SlaveHostResponse { slave: item.map(|(_, slave)| slave) })
+    }
+
+    pub fn list_guarded_wallets(
+        deps: Deps,
+        guardian: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<WalletsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after
+            .map(|host| Bound::exclusive((host, guardian.clone())));
+        let hosts = guardians()
+            .idx
+            .guardian
+            .prefix(guardian)
+            .keys(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+        // Hosts are only recorded for wallets of the master
+        let wallets = hosts
+            .into_iter()
+            .filter_map(|(host, _)| {
+                wallet_by_host(deps.storage, &host).transpose()
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(WalletsResponse { wallets })
+    }
+
+    pub fn get_stats(deps: Deps) -> StdResult<Stats> {
+        Ok(STATS.may_load(deps.storage)?.unwrap_or_default())
+    }
+
+    pub fn predict_host_address(
+        deps: Deps,
+        env: Env,
+        owner: String,
+        name: Option<String>,
+    ) -> StdResult<PredictHostAddressResponse> {
+        let owner = deps.api.addr_validate(&owner)?;
+        let name = wallet_name(name)
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+        let state = STATE.load(deps.storage)?;
+        let salt = host_salt(&owner, &name);
+        let address = predict_address(deps, &env, state.host_code_id, &salt)?;
+        Ok(PredictHostAddressResponse { address })
+    }
+
+    pub fn get_slave_code_id(
+        deps: Deps,
+        chain: String,
+    ) -> StdResult<GetSlaveCodeIdResponse> {
+        // Slaves of chains removed from the registry are not accepted
+        if !CHAINS.has(deps.storage, &chain) {
+            return Ok(GetSlaveCodeIdResponse { code_id: None });
+        }
+        let code_id = SLAVES.may_load(deps.storage, chain)?;
+        Ok(GetSlaveCodeIdResponse { code_id })
+    }
+
+    pub fn get_admin(deps: Deps) -> StdResult<AdminResponse> {
+        Ok(AdminResponse {
+            admin: ADMIN.load(deps.storage)?,
+            pending_admin: PENDING_ADMIN.may_load(deps.storage)?,
+        })
+    }
+
+    pub fn get_code_ids(deps: Deps) -> StdResult<CodeIdsResponse> {
+        let state = STATE.load(deps.storage)?;
+        let slave_code_ids = SLAVES
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        let deprecated = DEPRECATED_CODE_IDS
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(CodeIdsResponse {
+            host_code_id: state.host_code_id,
+            slave_code_ids,
+            deprecated,
+        })
+    }
 
-    pub fn get_host(deps: Deps, owner: Addr) -> StdResult<GetHostResponse> {
-        let wallet = WALLETS.load(deps.storage, owner)?;
-        Ok(GetHostResponse { host: wallet })
+    pub fn get_chain(
+        deps: Deps,
+        chain_id: String,
+    ) -> StdResult<ChainResponse> {
+        Ok(ChainResponse { chain: CHAINS.may_load(deps.storage, &chain_id)? })
+    }
+
+    pub fn list_chains(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<ListChainsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let chains = CHAINS
+            .range(
+                deps.storage,
+                start_after.as_deref().map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|item| item.map(|(_, chain)| chain))
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(ListChainsResponse { chains })
     }
 }
 
@@ -167,38 +900,970 @@ pub fn reply(
     match reply_kind.unwrap() {
         ReplyKind::ReplyCreateHost => {
             if let SubMsgResult::Ok(result) = msg.result {
-                let mut host_addr = None;
-                let mut owner_addr = None;
-                for ev in result.events {
-                    for attr in ev.attributes {
-                        if attr.key == "host_address" {
-                            host_addr = Some(attr.value);
-                        } else if attr.key == "owner" {
-                            owner_addr = Some(attr.value);
-                        }
-                    }
-                }
-                if host_addr.is_none() {
-                    return Err(ContractError::Generic(
-                        "Host address not detected.".to_string(),
-                    ));
-                }
-                if owner_addr.is_none() {
-                    return Err(ContractError::Generic(
-                        "Owner address not detected.".to_string(),
-                    ));
-                }
+                // Events may come from any contract the host calls, only
+                // the instantiate response and our own state are trusted
+                let pending = PENDING_HOST.may_load(deps.storage)?.ok_or(
+                    ContractError::Generic(
+                        "No host creation in progress.".to_string(),
+                    ),
+                )?;
+                PENDING_HOST.remove(deps.storage);
+                let data = result.data.ok_or(ContractError::Generic(
+                    "Instantiate response data missing.".to_string(),
+                ))?;
+                let response = parse_instantiate_response_data(&data)
+                    .map_err(|e| ContractError::Generic(e.to_string()))?;
                 let host_addr =
-                    deps.api.addr_validate(host_addr.unwrap().as_str())?;
-                let owner_addr =
-                    deps.api.addr_validate(owner_addr.unwrap().as_str())?;
-                WALLETS.save(deps.storage, owner_addr, &host_addr)?;
+                    deps.api.addr_validate(&response.contract_address)?;
+                if host_addr != pending.address {
+                    return Err(ContractError::HostAddressMismatch {
+                        address: host_addr.to_string(),
+                    });
+                }
+                let wallet = Wallet {
+                    owner: pending.owner,
+                    name: pending.name,
+                    host: host_addr.clone(),
+                };
+                save_wallet(deps.storage, &wallet)?;
+                save_guardians(deps.storage, &host_addr, &pending.guardians)?;
+                let mut stats =
+                    STATS.may_load(deps.storage)?.unwrap_or_default();
+                stats.wallets_created += 1;
+                STATS.save(deps.storage, &stats)?;
                 Ok(Response::new()
                     .add_attribute("contract", "master")
-                    .add_attribute("method", "reply_create_host"))
+                    .add_attribute("method", "reply_create_host")
+                    .add_attribute("host_address", host_addr))
             } else {
                 Err(ContractError::Generic("Failed".to_string()))
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{
+        AdminResponse, ChainResponse, CodeIdsResponse, GetHostResponse,
+        GetSlaveCodeIdResponse, ListWalletsResponse,
+        PredictHostAddressResponse, SlaveHostResponse, WalletByHostResponse,
+        WalletResponse, WalletsResponse,
+    };
+    use crate::state::Stats;
+    use cosmwasm_std::testing::{
+        mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        from_json, Api, CanonicalAddr, CodeInfoResponse, ContractResult,
+        CosmosMsg, HexBinary, OwnedDeps, RecoverPubkeyError, SubMsgResponse,
+        SystemResult, VerificationError, WasmQuery,
+    };
+    use std::marker::PhantomData;
+
+    /// Mock api that can also humanize instantiate2 addresses, which are
+    /// shown in hex.
+    #[derive(Clone, Copy, Default)]
+    struct TestApi(MockApi);
+
+    impl Api for TestApi {
+        fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+            let canonical = self.addr_canonicalize(human)?;
+            if self.addr_humanize(&canonical)? != human {
+                return Err(StdError::generic_err("Address not normalized"));
+            }
+            Ok(Addr::unchecked(human))
+        }
+
+        fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+            if human.len() < 3 {
+                return Err(StdError::generic_err("Address too short"));
+            }
+            Ok(human.as_bytes().into())
+        }
+
+        fn addr_humanize(
+            &self,
+            canonical: &CanonicalAddr,
+        ) -> StdResult<Addr> {
+            let bytes = canonical.as_slice();
+            match std::str::from_utf8(bytes) {
+                Ok(human)
+                    if human.chars().all(|c| c.is_ascii_alphanumeric()) =>
+                {
+                    Ok(Addr::unchecked(human))
+                }
+                _ => Ok(Addr::unchecked(HexBinary::from(bytes).to_hex())),
+            }
+        }
+
+        fn secp256k1_verify(
+            &self,
+            message_hash: &[u8],
+            signature: &[u8],
+            public_key: &[u8],
+        ) -> Result<bool, VerificationError> {
+            self.0.secp256k1_verify(message_hash, signature, public_key)
+        }
+
+        fn secp256k1_recover_pubkey(
+            &self,
+            message_hash: &[u8],
+            signature: &[u8],
+            recovery_param: u8,
+        ) -> Result<Vec<u8>, RecoverPubkeyError> {
+            self.0.secp256k1_recover_pubkey(
+                message_hash,
+                signature,
+                recovery_param,
+            )
+        }
+
+        fn ed25519_verify(
+            &self,
+            message: &[u8],
+            signature: &[u8],
+            public_key: &[u8],
+        ) -> Result<bool, VerificationError> {
+            self.0.ed25519_verify(message, signature, public_key)
+        }
+
+        fn ed25519_batch_verify(
+            &self,
+            messages: &[&[u8]],
+            signatures: &[&[u8]],
+            public_keys: &[&[u8]],
+        ) -> Result<bool, VerificationError> {
+            self.0.ed25519_batch_verify(messages, signatures, public_keys)
+        }
+
+        fn debug(&self, message: &str) {
+            self.0.debug(message)
+        }
+    }
+
+    type TestDeps = OwnedDeps<MockStorage, TestApi, MockQuerier>;
+
+    /// Dependencies where every code has a checksum derived from its id
+    /// and every host reports "slave" as its same-chain slave. Only
+    /// "hostone" reports its recovery pool.
+    fn mock_deps() -> TestDeps {
+        let mut querier = MockQuerier::default();
+        querier.update_wasm(|query| {
+            let res = match query {
+                WasmQuery::CodeInfo { code_id } => {
+                    let checksum = Sha256::digest(code_id.to_string());
+                    to_json_binary(&CodeInfoResponse::new(
+                        *code_id,
+                        "deployer".to_string(),
+                        HexBinary::from(checksum.as_slice()),
+                    ))
+                }
+                WasmQuery::Smart { contract_addr, msg } => {
+                    match from_json(msg).unwrap() {
+                        HostQueryMsg::GetSlave { .. } => {
+                            to_json_binary(&HostGetSlaveResponse {
+                                slave: Some(Addr::unchecked("slave")),
+                            })
+                        }
+                        HostQueryMsg::GetRecoveryPool {}
+                            if contract_addr == "hostone" =>
+                        {
+                            Ok(Binary::from(
+                                br#"{"members":[
+                                    {"addr":"carol","weight":1},
+                                    {"addr":"dave","chain":"bar-1"}
+                                ],"recovery_nonce":0}"#
+                                    .as_slice(),
+                            ))
+                        }
+                        HostQueryMsg::GetRecoveryPool {} => {
+                            return SystemResult::Ok(ContractResult::Err(
+                                "Unknown query".to_string(),
+                            ))
+                        }
+                    }
+                }
+                _ => panic!("Unexpected query {query:?}"),
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        });
+        OwnedDeps {
+            storage: MockStorage::default(),
+            api: TestApi::default(),
+            querier,
+            custom_query_type: PhantomData,
+        }
+    }
+
+    fn chain(chain_id: &str, slave_kind: SlaveKind) -> ChainInfo {
+        let remote = slave_kind != SlaveKind::Samechain;
+        ChainInfo {
+            chain_id: chain_id.to_string(),
+            bech32_prefix: "prefix".to_string(),
+            connection_id: remote.then(|| "connection-0".to_string()),
+            transfer_channel_id: None,
+            fee_denom: "token".to_string(),
+            slave_kind,
+        }
+    }
+
+    /// Master on "foo-1" with host code 1, slave code 2 for "foo-1" and 3
+    /// for "bar-1", both chains registered by "admin".
+    fn setup() -> TestDeps {
+        let mut deps = mock_deps();
+        let msg = InstantiateMsg {
+            host_code_id: 1,
+            host_chain: "foo-1".to_string(),
+            slave_code_ids: [
+                ("foo-1".to_string(), 2),
+                ("bar-1".to_string(), 3),
+            ]
+            .into(),
+            admin: None,
+        };
+        let admin = mock_info("admin", &[]);
+        instantiate(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap();
+        for chain in [
+            chain("foo-1", SlaveKind::Samechain),
+            chain("bar-1", SlaveKind::WasmIbc),
+        ] {
+            let msg = ExecuteMsg::RegisterChain { chain };
+            execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap();
+        }
+        deps
+    }
+
+    /// Successful instantiate2 reply for a host at `address`, encoded as
+    /// `MsgInstantiateContract2Response`.
+    fn host_reply(address: &str) -> Reply {
+        let mut data = vec![0x0a, address.len() as u8];
+        data.extend_from_slice(address.as_bytes());
+        Reply {
+            id: ReplyKind::ReplyCreateHost as u64,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data.into()),
+            }),
+        }
+    }
+
+    fn create_host_msg(name: Option<&str>) -> ExecuteMsg {
+        ExecuteMsg::CreateHost {
+            recovery_pool: vec![],
+            approval_pool: vec![],
+            recovery_approvals_needed: 0,
+            transfer_ownership_approvals_needed: 0,
+            proposal_approvals_needed: None,
+            recovery_delay: None,
+            recovery_lifetime: None,
+            owner_pubkey: None,
+            name: name.map(str::to_string),
+        }
+    }
+
+    /// Create a wallet of `owner` and complete it with the reply.
+    fn create_host(
+        deps: &mut TestDeps,
+        owner: &str,
+        name: Option<&str>,
+    ) -> Addr {
+        let info = mock_info(owner, &[]);
+        let msg = create_host_msg(name);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let address = PENDING_HOST.load(&deps.storage).unwrap().address;
+        reply(deps.as_mut(), mock_env(), host_reply(address.as_str()))
+            .unwrap();
+        address
+    }
+
+    fn slave_code_id(deps: &TestDeps, chain: &str) -> Option<CodeId> {
+        let msg = QueryMsg::GetSlaveCodeId { chain: chain.to_string() };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_json::<GetSlaveCodeIdResponse>(&res).unwrap().code_id
+    }
+
+    #[test]
+    fn chain_registry() {
+        let mut deps = setup();
+        let admin = mock_info("admin", &[]);
+
+        // Code ids only apply to registered chains
+        let msg = ExecuteMsg::SetSlaveCodeId {
+            chain: "baz-1".to_string(),
+            code_id: 4,
+        };
+        let res = execute(deps.as_mut(), mock_env(), admin.clone(), msg);
+        assert!(matches!(res, Err(ContractError::ChainNotFound {})));
+        assert_eq!(slave_code_id(&deps, "bar-1"), Some(3));
+
+        let msg = QueryMsg::GetChain { chain_id: "bar-1".to_string() };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: ChainResponse = from_json(res).unwrap();
+        assert_eq!(res.chain, Some(chain("bar-1", SlaveKind::WasmIbc)));
+
+        let msg = ExecuteMsg::RemoveChain { chain_id: "bar-1".to_string() };
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap();
+        assert_eq!(slave_code_id(&deps, "bar-1"), None);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCodeIds {});
+        let res: CodeIdsResponse = from_json(res.unwrap()).unwrap();
+        assert_eq!(res.slave_code_ids, vec![("foo-1".to_string(), 2)]);
+
+        // Registering again needs a new slave code
+        let msg = ExecuteMsg::RegisterChain {
+            chain: chain("bar-1", SlaveKind::WasmIbc),
+        };
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap();
+        assert_eq!(slave_code_id(&deps, "bar-1"), None);
+
+        let host = create_host(&mut deps, "owner", None);
+        let owner = mock_info("owner", &[]);
+        let create_slave = |chain: &str| ExecuteMsg::CreateSlave {
+            host_address: host.clone(),
+            slave_chain: chain.to_string(),
+            escape_hatch: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            create_slave("baz-1"),
+        );
+        assert!(matches!(res, Err(ContractError::ChainNotFound {})));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            create_slave("bar-1"),
+        );
+        assert!(matches!(res, Err(ContractError::NotImplemented(_))));
+
+        // Interchain accounts are opened by the host itself
+        let msg = ExecuteMsg::RegisterChain {
+            chain: chain("hub-4", SlaveKind::Ica),
+        };
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            create_slave("hub-4"),
+        );
+        assert!(matches!(res, Err(ContractError::NotImplemented(_))));
+        let res =
+            execute(deps.as_mut(), mock_env(), owner, create_slave("foo-1"))
+                .unwrap();
+        let CosmosMsg::Wasm(WasmMsg::Instantiate2 { code_id, .. }) =
+            &res.messages[0].msg
+        else {
+            panic!("Expected a slave instantiation");
+        };
+        assert_eq!(*code_id, 2);
+    }
+
+    #[test]
+    fn admin_transfer() {
+        let mut deps = setup();
+        let admin = mock_info("admin", &[]);
+        let new_admin = mock_info("successor", &[]);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            new_admin.clone(),
+            ExecuteMsg::AcceptAdmin {},
+        );
+        assert!(matches!(res, Err(ContractError::NoPendingAdmin {})));
+        let msg = ExecuteMsg::ProposeAdmin { admin: "successor".to_string() };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            msg.clone(),
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap();
+
+        // The current admin stays in charge until the transfer is accepted
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAdmin {});
+        let res: AdminResponse = from_json(res.unwrap()).unwrap();
+        assert_eq!(res.admin, "admin");
+        assert_eq!(res.pending_admin, Some(new_admin.sender.clone()));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            ExecuteMsg::AcceptAdmin {},
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            new_admin.clone(),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAdmin {});
+        let res: AdminResponse = from_json(res.unwrap()).unwrap();
+        assert_eq!(res.admin, new_admin.sender);
+        assert_eq!(res.pending_admin, None);
+
+        let msg = ExecuteMsg::RemoveChain { chain_id: "bar-1".to_string() };
+        let res = execute(deps.as_mut(), mock_env(), admin, msg.clone());
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+        execute(deps.as_mut(), mock_env(), new_admin, msg).unwrap();
+    }
+
+    #[test]
+    fn code_deprecation() {
+        let mut deps = setup();
+        let admin = mock_info("admin", &[]);
+        let deprecate = |code_id| ExecuteMsg::DeprecateCodeId { code_id };
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            deprecate(5),
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+        // Host and slave codes have to be replaced first
+        for code_id in [1, 2, 3] {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                admin.clone(),
+                deprecate(code_id),
+            );
+            assert!(
+                matches!(res, Err(ContractError::CodeIdInUse(id)) if id == code_id)
+            );
+        }
+        execute(deps.as_mut(), mock_env(), admin.clone(), deprecate(5))
+            .unwrap();
+
+        let msg = ExecuteMsg::SetHostCodeId { code_id: 5 };
+        let res = execute(deps.as_mut(), mock_env(), admin.clone(), msg);
+        assert!(matches!(res, Err(ContractError::CodeIdDeprecated(5))));
+        let msg = ExecuteMsg::SetSlaveCodeId {
+            chain: "foo-1".to_string(),
+            code_id: 5,
+        };
+        let res = execute(deps.as_mut(), mock_env(), admin.clone(), msg);
+        assert!(matches!(res, Err(ContractError::CodeIdDeprecated(5))));
+        for msg in [
+            MigrateMsg {
+                host_code_id: Some(5),
+                slave_code_ids: None,
+                admin: None,
+            },
+            MigrateMsg {
+                host_code_id: None,
+                slave_code_ids: Some([("bar-1".to_string(), 5)].into()),
+                admin: None,
+            },
+        ] {
+            let res = migrate(deps.as_mut(), mock_env(), msg);
+            assert!(matches!(res, Err(ContractError::CodeIdDeprecated(5))));
+        }
+
+        // Codes deprecated while in use by an earlier version are not used
+        let host = create_host(&mut deps, "owner", None);
+        SLAVES.save(&mut deps.storage, "foo-1".to_string(), &5).unwrap();
+        let owner = mock_info("owner", &[]);
+        let msg = ExecuteMsg::CreateSlave {
+            host_address: host,
+            slave_chain: "foo-1".to_string(),
+            escape_hatch: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), owner.clone(), msg);
+        assert!(matches!(res, Err(ContractError::CodeIdDeprecated(5))));
+        let msg = ExecuteMsg::MigrateWallet { name: None };
+        let res = execute(deps.as_mut(), mock_env(), owner, msg);
+        assert!(matches!(res, Err(ContractError::CodeIdDeprecated(5))));
+    }
+
+    #[test]
+    fn predict_host_address() {
+        let mut deps = setup();
+        let predict = |deps: &TestDeps, name: Option<&str>| {
+            let msg = QueryMsg::PredictHostAddress {
+                owner: "owner".to_string(),
+                name: name.map(str::to_string),
+            };
+            query(deps.as_ref(), mock_env(), msg)
+                .and_then(from_json::<PredictHostAddressResponse>)
+                .map(|res| res.address)
+        };
+
+        // Host code 1 instantiated by the master, salted by owner and name
+        let checksum = Sha256::digest("1");
+        let creator = deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap();
+        let salt = Sha256::digest("owner/default");
+        let expected = instantiate2_address(&checksum, &creator, &salt)
+            .map(|addr| deps.api.addr_humanize(&addr).unwrap())
+            .unwrap();
+        assert_eq!(predict(&deps, None).unwrap(), expected);
+        assert_eq!(predict(&deps, Some("default")).unwrap(), expected);
+        assert!(predict(&deps, Some("not/valid")).is_err());
+
+        for name in [None, Some("savings")] {
+            let predicted = predict(&deps, name).unwrap();
+            let info = mock_info("owner", &[]);
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                create_host_msg(name),
+            )
+            .unwrap();
+            let CosmosMsg::Wasm(WasmMsg::Instantiate2 { salt, .. }) =
+                &res.messages[0].msg
+            else {
+                panic!("Expected a host instantiation");
+            };
+            let name = name.unwrap_or(DEFAULT_WALLET_NAME);
+            assert_eq!(*salt, host_salt(&Addr::unchecked("owner"), name));
+            let pending = PENDING_HOST.load(&deps.storage).unwrap();
+            assert_eq!(pending.address, predicted);
+            reply(deps.as_mut(), mock_env(), host_reply(predicted.as_str()))
+                .unwrap();
+        }
+        assert_ne!(predict(&deps, Some("savings")).unwrap(), expected);
+    }
+
+    #[test]
+    fn create_host_reply() {
+        let mut deps = setup();
+        let info = mock_info("owner", &[]);
+
+        // Only replies to a pending creation are accepted
+        let res = reply(deps.as_mut(), mock_env(), host_reply("intruder"));
+        assert!(matches!(res, Err(ContractError::Generic(_))));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            create_host_msg(None),
+        )
+        .unwrap();
+        let pending = PENDING_HOST.load(&deps.storage).unwrap();
+        let mut missing_data = host_reply(pending.address.as_str());
+        if let SubMsgResult::Ok(response) = &mut missing_data.result {
+            response.data = None;
+        }
+        let res = reply(deps.as_mut(), mock_env(), missing_data);
+        assert!(matches!(res, Err(ContractError::Generic(_))));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            create_host_msg(None),
+        )
+        .unwrap();
+        let res = reply(deps.as_mut(), mock_env(), host_reply("intruder"));
+        assert!(matches!(
+            res,
+            Err(ContractError::HostAddressMismatch { address })
+                if address == "intruder"
+        ));
+        assert!(wallet_by_host(&deps.storage, &pending.address)
+            .unwrap()
+            .is_none());
+
+        // Instantiate data of the host follows the address as field 2
+        execute(deps.as_mut(), mock_env(), info, create_host_msg(None))
+            .unwrap();
+        let mut with_data = host_reply(pending.address.as_str());
+        if let SubMsgResult::Ok(response) = &mut with_data.result {
+            let mut data = response.data.take().unwrap().to_vec();
+            data.extend_from_slice(&[0x12, 0x02, 0xab, 0xcd]);
+            response.data = Some(data.into());
+        }
+        let res = reply(deps.as_mut(), mock_env(), with_data).unwrap();
+        assert!(res.attributes.iter().any(|attr| attr.key == "host_address"
+            && attr.value == pending.address.as_str()));
+        assert!(!PENDING_HOST.exists(&deps.storage));
+        let wallet =
+            wallet_by_host(&deps.storage, &pending.address).unwrap().unwrap();
+        assert_eq!(wallet.owner, "owner");
+        assert_eq!(wallet.name, DEFAULT_WALLET_NAME);
+        // A reply for the finished creation is rejected
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            host_reply(pending.address.as_str()),
+        );
+        assert!(matches!(res, Err(ContractError::Generic(_))));
+    }
+
+    fn list_wallets(deps: &TestDeps, owner: &str) -> ListWalletsResponse {
+        let msg = QueryMsg::ListWallets { owner: Addr::unchecked(owner) };
+        from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    }
+
+    fn get_host(deps: &TestDeps, owner: &str, name: Option<&str>) -> Addr {
+        let msg = QueryMsg::GetHostContract {
+            owner: Addr::unchecked(owner),
+            name: name.map(str::to_string),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_json::<GetHostResponse>(res).unwrap().host
+    }
+
+    fn update_owner(
+        deps: &mut TestDeps,
+        host: &Addr,
+        old_owner: &str,
+        new_owner: &str,
+    ) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::UpdateOwner {
+            old_owner: Addr::unchecked(old_owner),
+            new_owner: Addr::unchecked(new_owner),
+            recovery: false,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(host.as_str(), &[]), msg)
+    }
+
+    #[test]
+    fn named_wallets() {
+        let mut deps = setup();
+        let owner = mock_info("owner", &[]);
+
+        let main = create_host(&mut deps, "owner", None);
+        let savings = create_host(&mut deps, "owner", Some("savings"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            create_host_msg(Some("savings")),
+        );
+        assert!(matches!(res, Err(ContractError::WalletExists {})));
+        let long_name = "w".repeat(32);
+        for name in ["", "bad name", &format!("{long_name}w")] {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                owner.clone(),
+                create_host_msg(Some(name)),
+            );
+            assert!(matches!(res, Err(ContractError::InvalidWalletName {})));
+        }
+        let res = list_wallets(&deps, "owner");
+        assert_eq!(res.default, Some(DEFAULT_WALLET_NAME.to_string()));
+        assert_eq!(
+            res.wallets,
+            vec![
+                WalletResponse {
+                    name: DEFAULT_WALLET_NAME.to_string(),
+                    host: main.clone()
+                },
+                WalletResponse {
+                    name: "savings".to_string(),
+                    host: savings.clone()
+                },
+            ]
+        );
+        assert_eq!(get_host(&deps, "owner", None), main);
+        assert_eq!(get_host(&deps, "owner", Some("savings")), savings);
+
+        let msg = ExecuteMsg::SetDefaultWallet { name: "other".to_string() };
+        let res = execute(deps.as_mut(), mock_env(), owner.clone(), msg);
+        assert!(matches!(res, Err(ContractError::WalletNotFound {})));
+        let msg =
+            ExecuteMsg::SetDefaultWallet { name: "savings".to_string() };
+        execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+        assert_eq!(get_host(&deps, "owner", None), savings);
+
+        // Only the host moves itself, from its current owner
+        let res = update_owner(&mut deps, &main, "stranger", "heir");
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+        let res = update_owner(
+            &mut deps,
+            &Addr::unchecked("stranger"),
+            "owner",
+            "heir",
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+
+        // Moving the default wallet away picks another one
+        update_owner(&mut deps, &savings, "owner", "heir").unwrap();
+        assert_eq!(get_host(&deps, "owner", None), main);
+        assert_eq!(get_host(&deps, "heir", None), savings);
+
+        // Taken names get a suffix and stay valid
+        create_host(&mut deps, "heir", Some("vault"));
+        let second = create_host(&mut deps, "owner", Some("vault"));
+        create_host(&mut deps, "heir", Some(&long_name));
+        let long = create_host(&mut deps, "owner", Some(&long_name));
+        for host in [&second, &long] {
+            update_owner(&mut deps, host, "owner", "heir").unwrap();
+        }
+        update_owner(&mut deps, &main, "owner", "heir").unwrap();
+        let res = list_wallets(&deps, "owner");
+        assert_eq!(res.default, None);
+        assert!(res.wallets.is_empty());
+
+        let res = list_wallets(&deps, "heir");
+        assert_eq!(res.default, Some("savings".to_string()));
+        assert_eq!(res.wallets.len(), 6);
+        for wallet in &res.wallets {
+            assert!(wallet_name(Some(wallet.name.clone())).is_ok());
+        }
+        let renamed = |host: &Addr| {
+            res.wallets.iter().find(|w| w.host == *host).unwrap().name.clone()
+        };
+        assert_eq!(renamed(&main), DEFAULT_WALLET_NAME);
+        assert!(renamed(&second).starts_with("vault-"));
+        assert_eq!(renamed(&long).len(), 32);
+        assert_ne!(renamed(&long), long_name);
+    }
+
+    #[test]
+    fn legacy_wallets() {
+        let mut deps = setup();
+        for (owner, host) in [("alice", "hostone"), ("bob", "hosttwo")] {
+            LEGACY_WALLETS
+                .save(
+                    &mut deps.storage,
+                    Addr::unchecked(owner),
+                    &Addr::unchecked(host),
+                )
+                .unwrap();
+        }
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "0.3.0")
+            .unwrap();
+        let msg = MigrateMsg {
+            host_code_id: None,
+            slave_code_ids: None,
+            admin: None,
+        };
+        let migrated = migrate(deps.as_mut(), mock_env(), msg).unwrap();
+
+        let res = list_wallets(&deps, "alice");
+        assert_eq!(res.default, Some(DEFAULT_WALLET_NAME.to_string()));
+        assert_eq!(
+            res.wallets,
+            vec![WalletResponse {
+                name: DEFAULT_WALLET_NAME.to_string(),
+                host: Addr::unchecked("hostone"),
+            }]
+        );
+        assert_eq!(get_host(&deps, "bob", None), "hosttwo");
+        let wallet =
+            wallet_by_host(&deps.storage, &Addr::unchecked("hosttwo"))
+                .unwrap();
+        assert_eq!(wallet.map(|w| w.owner), Some(Addr::unchecked("bob")));
+        assert!(LEGACY_WALLETS.is_empty(&deps.storage));
+        assert_eq!(STATS.load(&deps.storage).unwrap().wallets_created, 2);
+
+        // Guardians are indexed where the host reports them
+        let owners = |guardian: &str| -> Vec<Addr> {
+            guarded_wallets(&deps, guardian, None, None)
+                .into_iter()
+                .map(|w| w.owner)
+                .collect()
+        };
+        assert_eq!(owners("carol"), vec![Addr::unchecked("alice")]);
+        assert_eq!(owners("bar-1/dave"), vec![Addr::unchecked("alice")]);
+        assert!(owners("dave").is_empty());
+        let unindexed = migrated.attributes.last().unwrap();
+        assert_eq!(unindexed.value, "1");
+        let version = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(version.version, CONTRACT_VERSION);
+    }
+
+    fn guarded_wallets(
+        deps: &TestDeps,
+        guardian: &str,
+        start_after: Option<&Addr>,
+        limit: Option<u32>,
+    ) -> Vec<Wallet> {
+        let msg = QueryMsg::ListGuardedWallets {
+            guardian: Addr::unchecked(guardian),
+            start_after: start_after.cloned(),
+            limit,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        from_json::<WalletsResponse>(res).unwrap().wallets
+    }
+
+    #[test]
+    fn wallet_indexes() {
+        let mut deps = setup();
+
+        let mut msg = create_host_msg(None);
+        if let ExecuteMsg::CreateHost { recovery_pool, .. } = &mut msg {
+            for (guardian, chain) in
+                [("guardian", None), ("remote", Some("bar-1"))]
+            {
+                recovery_pool.push(RecoveryMember {
+                    addr: Addr::unchecked(guardian),
+                    weight: 1,
+                    required: false,
+                    pubkey: None,
+                    chain: chain.map(str::to_string),
+                });
+            }
+        }
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg)
+            .unwrap();
+        let alice = PENDING_HOST.load(&deps.storage).unwrap().address;
+        reply(deps.as_mut(), mock_env(), host_reply(alice.as_str())).unwrap();
+        let bob = create_host(&mut deps, "bob", None);
+        let carol = create_host(&mut deps, "carol", None);
+        for host in [&bob, &carol] {
+            let msg = ExecuteMsg::UpdateGuardians {
+                guardians: vec![Addr::unchecked("guardian")],
+            };
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(host.as_str(), &[]),
+                msg,
+            )
+            .unwrap();
+        }
+        let msg = ExecuteMsg::UpdateGuardians { guardians: vec![] };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            msg,
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+
+        // Guarded wallets are ordered by host
+        let mut hosts = [alice.clone(), bob.clone(), carol.clone()];
+        hosts.sort();
+        let page = guarded_wallets(&deps, "guardian", None, Some(2));
+        assert_eq!(
+            page.iter().map(|w| &w.host).collect_vec(),
+            [&hosts[0], &hosts[1]]
+        );
+        let page =
+            guarded_wallets(&deps, "guardian", Some(&hosts[1]), Some(2));
+        assert_eq!(page.iter().map(|w| &w.host).collect_vec(), [&hosts[2]]);
+        let page = guarded_wallets(&deps, "bar-1/remote", None, None);
+        assert_eq!(page.iter().map(|w| &w.host).collect_vec(), [&alice]);
+
+        // The pool is replaced, not merged
+        let msg = ExecuteMsg::UpdateGuardians {
+            guardians: vec![Addr::unchecked("friend")],
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(alice.as_str(), &[]),
+            msg,
+        )
+        .unwrap();
+        assert!(guarded_wallets(&deps, "bar-1/remote", None, None).is_empty());
+        let page = guarded_wallets(&deps, "guardian", None, None);
+        assert!(page.iter().all(|w| w.host != alice));
+        assert_eq!(page.len(), 2);
+
+        // Recovery moves the wallet and its host index to the new owner
+        let msg = ExecuteMsg::UpdateOwner {
+            old_owner: Addr::unchecked("alice"),
+            new_owner: Addr::unchecked("dave"),
+            recovery: true,
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(alice.as_str(), &[]),
+            msg,
+        )
+        .unwrap();
+        let msg = QueryMsg::GetWalletByHost { host: alice.clone() };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let wallet = from_json::<WalletByHostResponse>(res).unwrap().wallet;
+        assert_eq!(wallet.map(|w| w.owner), Some(Addr::unchecked("dave")));
+        let page = guarded_wallets(&deps, "friend", None, None);
+        assert_eq!(page[0].owner, "dave");
+
+        // Paging continues after the last wallet of the previous page
+        let list_all = |start_after: Option<&Wallet>| {
+            let msg = QueryMsg::ListAllWallets {
+                start_after: start_after
+                    .map(|w| (w.owner.clone(), w.name.clone())),
+                limit: Some(2),
+            };
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            from_json::<WalletsResponse>(res).unwrap().wallets
+        };
+        let first = list_all(None);
+        assert_eq!(first.len(), 2);
+        let second = list_all(first.last());
+        assert_eq!(second.len(), 1);
+        assert!(list_all(second.last()).is_empty());
+        let owners = first
+            .iter()
+            .chain(&second)
+            .map(|w| w.owner.as_str())
+            .sorted()
+            .collect_vec();
+        assert_eq!(owners, ["bob", "carol", "dave"]);
+
+        // Slaves are found by their predicted address
+        let msg = ExecuteMsg::CreateSlave {
+            host_address: bob.clone(),
+            slave_chain: "foo-1".to_string(),
+            escape_hatch: None,
+        };
+        let res =
+            execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg)
+                .unwrap();
+        let CosmosMsg::Wasm(WasmMsg::Instantiate2 { code_id, salt, .. }) =
+            &res.messages[0].msg
+        else {
+            panic!("Expected a slave instantiation");
+        };
+        let slave =
+            predict_address(deps.as_ref(), &mock_env(), *code_id, salt)
+                .unwrap();
+        let slave_host = |deps: &TestDeps, slave: &Addr| {
+            let msg = QueryMsg::GetSlaveHost { slave: slave.clone() };
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            from_json::<SlaveHostResponse>(res).unwrap().slave
+        };
+        let found = slave_host(&deps, &slave).unwrap();
+        assert_eq!(
+            (found.host, found.chain.as_str()),
+            (bob.clone(), "foo-1")
+        );
+
+        // A replacement gets a fresh address and takes over the chain
+        let msg = ExecuteMsg::CreateSlave {
+            host_address: bob.clone(),
+            slave_chain: "foo-1".to_string(),
+            escape_hatch: None,
+        };
+        let res =
+            execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg)
+                .unwrap();
+        let CosmosMsg::Wasm(WasmMsg::Instantiate2 { code_id, salt, .. }) =
+            &res.messages[0].msg
+        else {
+            panic!("Expected a slave instantiation");
+        };
+        let replacement =
+            predict_address(deps.as_ref(), &mock_env(), *code_id, salt)
+                .unwrap();
+        assert_ne!(replacement, slave);
+        assert_eq!(slave_host(&deps, &slave), None);
+        assert_eq!(slave_host(&deps, &replacement).unwrap().host, bob);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStats {});
+        let stats: Stats = from_json(res.unwrap()).unwrap();
+        assert_eq!(
+            stats,
+            Stats { wallets_created: 3, recoveries_completed: 1 }
+        );
+    }
+}
@@ -15,6 +15,45 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Invalid chain: {0}")]
+    InvalidChain(String),
+
+    #[error("Chain is not registered.")]
+    ChainNotFound {},
+
+    #[error("Code id {0} is deprecated.")]
+    CodeIdDeprecated(u64),
+
+    #[error("Code id {0} is still used for new wallets.")]
+    CodeIdInUse(u64),
+
+    #[error("No admin transfer in progress.")]
+    NoPendingAdmin {},
+
+    #[error("Admin must be set when migrating to the chain registry.")]
+    AdminRequired {},
+
     #[error("{0}")]
     Generic(String),
+
+    #[error("Wallet names are 1 to 32 letters, digits, '-' or '_'.")]
+    InvalidWalletName {},
+
+    #[error("Owner already has a wallet with this name.")]
+    WalletExists {},
+
+    #[error("Wallet not found.")]
+    WalletNotFound {},
+
+    #[error("Host was created at unexpected address {address}.")]
+    HostAddressMismatch { address: String },
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from newer version {previous_version}")]
+    CannotDowngrade { previous_version: String },
+
+    #[error("Invalid contract version: {0}")]
+    InvalidVersion(String),
 }
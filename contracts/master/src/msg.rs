@@ -1,47 +1,200 @@
-use crate::state::CodeId;
+use crate::state::{ChainInfo, CodeId, SlaveContract, Stats, Wallet};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Binary};
+use serde::Deserialize;
 use std::collections::HashMap;
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub host_code_id: CodeId,
     pub host_chain: String,
+    /// Slave code per chain id, used once the chain is registered.
     pub slave_code_ids: HashMap<String, CodeId>,
+    /// Manages the chain registry, defaults to the sender.
+    pub admin: Option<String>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
     CreateHost {
-        recovery_pool: Vec<Addr>,
+        recovery_pool: Vec<RecoveryMember>,
         approval_pool: Vec<Addr>,
         recovery_approvals_needed: u32,
         transfer_ownership_approvals_needed: u32,
+        proposal_approvals_needed: Option<u32>,
+        recovery_delay: Option<u64>,
+        recovery_lifetime: Option<u64>,
+        owner_pubkey: Option<Binary>,
+        /// Defaults to "default", the first wallet becomes the default.
+        name: Option<String>,
     },
     CreateSlave {
         host_address: Addr,
         slave_chain: String,
+        /// Lets a backup or guardians withdraw the slave balance if the
+        /// host goes silent, later changed by the host through a batch.
+        escape_hatch: Option<EscapeHatch>,
     },
+    /// Sent by the host when its ownership changes.
     UpdateOwner {
         old_owner: Addr,
         new_owner: Addr,
+        /// Ownership changed by social recovery, only counted in the stats
+        /// and not verified by the master.
+        #[serde(default)]
+        recovery: bool,
     },
+    /// Sent by the host when its recovery pool changes.
+    UpdateGuardians {
+        guardians: Vec<Addr>,
+    },
+    /// Upgrade a host of the sender and its same-chain slave to the
+    /// current code, the default wallet unless `name` is given.
+    /// Only wallets created with master as admin can be migrated.
+    MigrateWallet {
+        name: Option<String>,
+    },
+    SetDefaultWallet {
+        name: String,
+    },
+    /// Add or replace a chain in the registry, admin only.
+    RegisterChain {
+        chain: ChainInfo,
+    },
+    /// Admin only.
+    RemoveChain {
+        chain_id: String,
+    },
+    /// Code for new hosts and `MigrateWallet`, admin only.
+    SetHostCodeId {
+        code_id: CodeId,
+    },
+    /// Slave code of a registered `chain`, admin only.
+    SetSlaveCodeId {
+        chain: String,
+        code_id: CodeId,
+    },
+    /// Forbid the code for new hosts and slaves, admin only.
+    DeprecateCodeId {
+        code_id: CodeId,
+    },
+    /// Offer the admin role to `admin`, admin only.
+    ProposeAdmin {
+        admin: String,
+    },
+    /// Sent by the proposed admin.
+    AcceptAdmin {},
+}
+
+#[cw_serde]
+pub struct MigrateMsg {
+    /// Code used for new hosts and `MigrateWallet`.
+    pub host_code_id: Option<CodeId>,
+    /// Slave code per chain, merged into the existing ones.
+    pub slave_code_ids: Option<HashMap<String, CodeId>>,
+    /// Required when migrating from a version without the chain registry.
+    pub admin: Option<String>,
+}
+
+#[cw_serde]
+pub struct RecoveryMember {
+    pub addr: Addr,
+    pub weight: u32,
+    pub required: bool,
+    pub pubkey: Option<GuardianPubkey>,
+    pub chain: Option<String>,
+}
+
+impl RecoveryMember {
+    /// Identity the host records the member under, remote guardians are
+    /// prefixed with their chain.
+    pub fn voter(&self) -> Addr {
+        voter(&self.addr, self.chain.as_deref())
+    }
+}
+
+fn voter(addr: &Addr, chain: Option<&str>) -> Addr {
+    match chain {
+        Some(chain) => Addr::unchecked(format!("{chain}/{addr}")),
+        None => addr.clone(),
+    }
+}
+
+#[cw_serde]
+pub enum GuardianPubkey {
+    Secp256k1(Binary),
+    Ed25519(Binary),
 }
 
 #[cw_serde]
 pub struct HostInstantiateMsg {
-    pub recovery_pool: Vec<Addr>,
+    pub recovery_pool: Vec<RecoveryMember>,
     pub approval_pool: Vec<Addr>,
     pub recovery_approvals_needed: u32,
     pub transfer_ownership_approvals_needed: u32,
+    pub proposal_approvals_needed: Option<u32>,
+    pub recovery_delay: Option<u64>,
+    pub recovery_lifetime: Option<u64>,
+    pub owner_pubkey: Option<Binary>,
     pub owner: Addr,
     pub chain: String,
 }
 
+#[cw_serde]
+pub struct HostMigrateMsg {}
+
+#[cw_serde]
+pub struct SlaveMigrateMsg {}
+
+#[cw_serde]
+pub enum HostQueryMsg {
+    GetSlave { chain: String },
+    GetRecoveryPool {},
+}
+
+#[cw_serde]
+pub struct HostGetSlaveResponse {
+    pub slave: Option<Addr>,
+}
+
+/// Members out of the host `GetRecoveryPool` response, the other fields
+/// differ between host versions.
+#[derive(Deserialize)]
+pub struct HostRecoveryPoolResponse {
+    pub members: Vec<HostRecoveryMember>,
+}
+
+#[derive(Deserialize)]
+pub struct HostRecoveryMember {
+    pub addr: Addr,
+    /// Missing before remote guardians.
+    pub chain: Option<String>,
+}
+
+impl HostRecoveryMember {
+    pub fn voter(&self) -> Addr {
+        voter(&self.addr, self.chain.as_deref())
+    }
+}
+
 #[cw_serde]
 pub struct SlaveInstantiateMsg {
     pub owner: Addr,
     pub chain: String,
+    pub escape_hatch: Option<EscapeHatch>,
+}
+
+#[cw_serde]
+pub struct EscapeHatch {
+    pub backup: Option<Addr>,
+    pub guardians: Option<EscapeGuardians>,
+    pub inactivity: u64,
+}
+
+#[cw_serde]
+pub struct EscapeGuardians {
+    pub members: Vec<Addr>,
+    pub threshold: u32,
 }
 
 // TODO: get rid of this shitty "recommended" layout
@@ -50,11 +203,120 @@ pub struct SlaveInstantiateMsg {
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
+    /// Default wallet of `owner` unless `name` is given.
     #[returns(GetHostResponse)]
-    GetHostContract { owner: Addr },
+    GetHostContract { owner: Addr, name: Option<String> },
+
+    #[returns(ListWalletsResponse)]
+    ListWallets { owner: Addr },
+
+    /// Wallets of all owners, paged by owner and name.
+    #[returns(WalletsResponse)]
+    ListAllWallets { start_after: Option<(Addr, String)>, limit: Option<u32> },
+
+    /// Owner and name of the wallet with this host.
+    #[returns(WalletByHostResponse)]
+    GetWalletByHost { host: Addr },
+
+    /// Host of a slave created by the master.
+    #[returns(SlaveHostResponse)]
+    GetSlaveHost { slave: Addr },
+
+    /// Wallets with `guardian` in their recovery pool, ordered by host.
+    /// Remote guardians are given as "chain/address".
+    #[returns(WalletsResponse)]
+    ListGuardedWallets {
+        guardian: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+
+    #[returns(Stats)]
+    GetStats {},
+
+    /// Address `CreateHost` sent by `owner` instantiates the host at.
+    #[returns(PredictHostAddressResponse)]
+    PredictHostAddress { owner: String, name: Option<String> },
+
+    /// Slave code id used for `chain`, hosts rely on it to authenticate
+    /// slave registrations. Unset for chains missing from the registry.
+    #[returns(GetSlaveCodeIdResponse)]
+    GetSlaveCodeId { chain: String },
+
+    #[returns(AdminResponse)]
+    GetAdmin {},
+
+    #[returns(CodeIdsResponse)]
+    GetCodeIds {},
+
+    #[returns(ChainResponse)]
+    GetChain { chain_id: String },
+
+    #[returns(ListChainsResponse)]
+    ListChains { start_after: Option<String>, limit: Option<u32> },
 }
 
 #[cw_serde]
 pub struct GetHostResponse {
     pub host: Addr,
 }
+
+#[cw_serde]
+pub struct WalletResponse {
+    pub name: String,
+    pub host: Addr,
+}
+
+#[cw_serde]
+pub struct ListWalletsResponse {
+    pub default: Option<String>,
+    pub wallets: Vec<WalletResponse>,
+}
+
+#[cw_serde]
+pub struct WalletsResponse {
+    pub wallets: Vec<Wallet>,
+}
+
+#[cw_serde]
+pub struct WalletByHostResponse {
+    pub wallet: Option<Wallet>,
+}
+
+#[cw_serde]
+pub struct SlaveHostResponse {
+    pub slave: Option<SlaveContract>,
+}
+
+#[cw_serde]
+pub struct PredictHostAddressResponse {
+    pub address: Addr,
+}
+
+#[cw_serde]
+pub struct GetSlaveCodeIdResponse {
+    pub code_id: Option<CodeId>,
+}
+
+#[cw_serde]
+pub struct AdminResponse {
+    pub admin: Addr,
+    pub pending_admin: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct CodeIdsResponse {
+    pub host_code_id: CodeId,
+    pub slave_code_ids: Vec<(String, CodeId)>,
+    pub deprecated: Vec<CodeId>,
+}
+
+#[cw_serde]
+pub struct ChainResponse {
+    pub chain: Option<ChainInfo>,
+}
+
+#[cw_serde]
+pub struct ListChainsResponse {
+    pub chains: Vec<ChainInfo>,
+}
@@ -1,5 +1,7 @@
 use cosmwasm_std::Addr;
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{
+    Index, IndexList, IndexedMap, Item, Map, MultiIndex, UniqueIndex,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -11,7 +13,168 @@ pub struct State {
     pub host_chain: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ChainInfo {
+    pub chain_id: String,
+    pub bech32_prefix: String,
+    /// IBC connection from the host chain, unset for the host chain.
+    pub connection_id: Option<String>,
+    /// ICS-20 transfer channel from the host chain.
+    pub transfer_channel_id: Option<String>,
+    pub fee_denom: String,
+    pub slave_kind: SlaveKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SlaveKind {
+    /// Instantiated by the master on the host chain.
+    Samechain,
+    /// `slave_ibc` deployed on a CosmWasm chain.
+    WasmIbc,
+    /// ICS-27 interchain account opened by the host over the connection.
+    Ica,
+}
+
+/// Host instantiated by `CreateHost`, checked against the reply.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingHost {
+    pub owner: Addr,
+    pub name: String,
+    /// Predicted instantiate2 address.
+    pub address: Addr,
+    pub guardians: Vec<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Wallet {
+    pub owner: Addr,
+    pub name: String,
+    pub host: Addr,
+}
+
+/// Slave instantiated by `CreateSlave`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SlaveContract {
+    pub host: Addr,
+    pub chain: String,
+    pub address: Addr,
+}
+
+/// Recovery member of a host, as reported by the host.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Guardian {
+    pub host: Addr,
+    pub guardian: Addr,
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema,
+)]
+pub struct Stats {
+    pub wallets_created: u64,
+    /// Counted from the `recovery` flag of `UpdateOwner`, which the host
+    /// sets itself and the master cannot verify.
+    pub recoveries_completed: u64,
+}
+
+pub struct WalletIndexes<'a> {
+    /// host -> wallet
+    pub host: UniqueIndex<'a, Addr, Wallet, (Addr, String)>,
+}
+
+impl<'a> IndexList<Wallet> for WalletIndexes<'a> {
+    fn get_indexes(
+        &'_ self,
+    ) -> Box<dyn Iterator<Item = &'_ dyn Index<Wallet>> + '_> {
+        let v: Vec<&dyn Index<Wallet>> = vec![&self.host];
+        Box::new(v.into_iter())
+    }
+}
+
+pub struct SlaveIndexes<'a> {
+    /// slave -> (host, chain)
+    pub address: UniqueIndex<'a, Addr, SlaveContract, (Addr, String)>,
+}
+
+impl<'a> IndexList<SlaveContract> for SlaveIndexes<'a> {
+    fn get_indexes(
+        &'_ self,
+    ) -> Box<dyn Iterator<Item = &'_ dyn Index<SlaveContract>> + '_> {
+        let v: Vec<&dyn Index<SlaveContract>> = vec![&self.address];
+        Box::new(v.into_iter())
+    }
+}
+
+pub struct GuardianIndexes<'a> {
+    /// guardian -> hosts it guards
+    pub guardian: MultiIndex<'a, Addr, Guardian, (Addr, Addr)>,
+}
+
+impl<'a> IndexList<Guardian> for GuardianIndexes<'a> {
+    fn get_indexes(
+        &'_ self,
+    ) -> Box<dyn Iterator<Item = &'_ dyn Index<Guardian>> + '_> {
+        let v: Vec<&dyn Index<Guardian>> = vec![&self.guardian];
+        Box::new(v.into_iter())
+    }
+}
+
 pub const STATE: Item<State> = Item::new("state");
+pub const PENDING_HOST: Item<PendingHost> = Item::new("pending_host");
+/// Manages the chain registry and code ids, may be a multisig.
+pub const ADMIN: Item<Addr> = Item::new("admin");
+/// Proposed admin, who has to accept the role.
+pub const PENDING_ADMIN: Item<Addr> = Item::new("pending_admin");
+/// Code ids that can no longer be used for new hosts and slaves.
+pub const DEPRECATED_CODE_IDS: Map<CodeId, bool> =
+    Map::new("deprecated_code_ids");
+/// chain id -> slave code, only used while the chain is registered
 pub const SLAVES: Map<String, CodeId> = Map::new("slaves");
+/// chain id -> chain supported by the wallets
+pub const CHAINS: Map<&str, ChainInfo> = Map::new("chains");
+/// host -> slaves created so far, salts replacements with a fresh address
+pub const SLAVE_COUNTS: Map<&Addr, u64> = Map::new("slave_counts");
+
+/// Name of the wallet created when the owner gives none.
+pub const DEFAULT_WALLET_NAME: &str = "default";
+
+/// (owner, wallet name) -> wallet
+pub fn wallets<'a>(
+) -> IndexedMap<'a, (&'a Addr, &'a str), Wallet, WalletIndexes<'a>> {
+    let indexes = WalletIndexes {
+        host: UniqueIndex::new(|w| w.host.clone(), "named_wallets__host"),
+    };
+    IndexedMap::new("named_wallets", indexes)
+}
+
+/// (host, chain) -> slave created by the master
+pub fn slave_contracts<'a>(
+) -> IndexedMap<'a, (&'a Addr, &'a str), SlaveContract, SlaveIndexes<'a>> {
+    let indexes = SlaveIndexes {
+        address: UniqueIndex::new(
+            |s| s.address.clone(),
+            "slave_contracts__address",
+        ),
+    };
+    IndexedMap::new("slave_contracts", indexes)
+}
+
+/// (host, guardian) -> guardian, kept up to date by the hosts
+pub fn guardians<'a>(
+) -> IndexedMap<'a, (&'a Addr, &'a Addr), Guardian, GuardianIndexes<'a>> {
+    let indexes = GuardianIndexes {
+        guardian: MultiIndex::new(
+            |_, g| g.guardian.clone(),
+            "guardians",
+            "guardians__guardian",
+        ),
+    };
+    IndexedMap::new("guardians", indexes)
+}
 
-pub const WALLETS: Map<Addr, Addr> = Map::new("wallets");
+pub const STATS: Item<Stats> = Item::new("stats");
+/// owner -> name of the wallet used when none is given
+pub const DEFAULT_WALLETS: Map<&Addr, String> = Map::new("default_wallets");
+/// owner -> host, a single wallet per owner before 0.4.0
+pub const LEGACY_WALLETS: Map<Addr, Addr> = Map::new("wallets");
@@ -0,0 +1,20 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EscapeError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("No escape hatch is configured.")]
+    NoEscapeHatch {},
+
+    #[error("Host was active too recently to escape.")]
+    HostStillActive {},
+
+    #[error("Invalid escape hatch: {0}")]
+    InvalidEscapeHatch(String),
+}
@@ -0,0 +1,352 @@
+//! Escape hatch shared by the slave contracts: the balance of a slave
+//! whose host went silent can be withdrawn by a backup or a quorum of
+//! guardians.
+
+mod error;
+pub mod msg;
+pub mod state;
+
+use cosmwasm_std::{
+    Addr, Api, BankMsg, Deps, DepsMut, Env, MessageInfo, Order, Response,
+    StdResult, Storage, Timestamp,
+};
+
+pub use crate::error::EscapeError;
+use crate::msg::EscapeHatch;
+use crate::state::{
+    EscapeApproval, PendingEscapeHatch, ESCAPE_APPROVALS, ESCAPE_HATCH,
+    LAST_HOST_CONTACT, PENDING_ESCAPE_HATCH,
+};
+
+/// Shortest silence of the host after which a hatch may open.
+pub const MIN_INACTIVITY: u64 = 7 * 24 * 60 * 60;
+/// Same timelock as loosening a spending limit on the host, which gives
+/// guardians time to recover a wallet whose owner key leaked.
+pub const ESCAPE_HATCH_DELAY: u64 = 2 * 24 * 60 * 60;
+
+pub fn validate(
+    api: &dyn Api,
+    hatch: &EscapeHatch,
+) -> Result<(), EscapeError> {
+    let invalid = |reason: &str| {
+        Err(EscapeError::InvalidEscapeHatch(reason.to_string()))
+    };
+    if hatch.inactivity < MIN_INACTIVITY {
+        return invalid("inactivity is too short");
+    }
+    if let Some(backup) = &hatch.backup {
+        api.addr_validate(backup.as_str())?;
+    }
+    match &hatch.guardians {
+        Some(guardians) => {
+            for (i, member) in guardians.members.iter().enumerate() {
+                api.addr_validate(member.as_str())?;
+                if guardians.members[..i].contains(member) {
+                    return invalid("duplicate guardian");
+                }
+            }
+            let max = guardians.members.len() as u32;
+            if guardians.threshold == 0 || guardians.threshold > max {
+                return invalid("threshold must be between 1 and guardians");
+            }
+        }
+        None if hatch.backup.is_none() => {
+            return invalid("a backup or guardians are required")
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Start counting inactivity and set the hatch chosen at creation, the
+/// slave holds nothing yet.
+pub fn init(
+    deps: DepsMut,
+    env: &Env,
+    hatch: Option<EscapeHatch>,
+) -> Result<(), EscapeError> {
+    host_contact(deps.storage, env)?;
+    if let Some(hatch) = hatch {
+        validate(deps.api, &hatch)?;
+        ESCAPE_HATCH.save(deps.storage, &hatch)?;
+    }
+    Ok(())
+}
+
+/// Record that the host is still alive.
+pub fn host_contact(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    LAST_HOST_CONTACT.save(storage, &env.block.time)
+}
+
+/// Hatch in effect, after applying a pending one whose timelock passed.
+fn load_hatch(
+    storage: &mut dyn Storage,
+    env: &Env,
+) -> StdResult<Option<EscapeHatch>> {
+    if let Some(pending) = PENDING_ESCAPE_HATCH.may_load(storage)? {
+        if pending.effective_at <= env.block.time {
+            PENDING_ESCAPE_HATCH.remove(storage);
+            ESCAPE_APPROVALS.clear(storage);
+            ESCAPE_HATCH.save(storage, &pending.hatch)?;
+            return Ok(Some(pending.hatch));
+        }
+    }
+    ESCAPE_HATCH.may_load(storage)
+}
+
+/// Disabling the hatch or keeping the active one is immediate, anything
+/// else waits for [`ESCAPE_HATCH_DELAY`] while the previous hatch stays.
+pub fn set_escape_hatch(
+    deps: DepsMut,
+    env: Env,
+    hatch: Option<EscapeHatch>,
+) -> Result<Response, EscapeError> {
+    let response = Response::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "set_escape_hatch");
+    let Some(hatch) = hatch else {
+        ESCAPE_HATCH.remove(deps.storage);
+        PENDING_ESCAPE_HATCH.remove(deps.storage);
+        ESCAPE_APPROVALS.clear(deps.storage);
+        return Ok(response);
+    };
+    validate(deps.api, &hatch)?;
+    let mut response =
+        response.add_attribute("inactivity", hatch.inactivity.to_string());
+    if let Some(backup) = &hatch.backup {
+        response = response.add_attribute("backup", backup);
+    }
+    if load_hatch(deps.storage, &env)?.as_ref() == Some(&hatch) {
+        // Also drops a change still waiting for its timelock
+        PENDING_ESCAPE_HATCH.remove(deps.storage);
+        return Ok(response);
+    }
+    let pending = PendingEscapeHatch {
+        hatch,
+        effective_at: env.block.time.plus_seconds(ESCAPE_HATCH_DELAY),
+    };
+    PENDING_ESCAPE_HATCH.save(deps.storage, &pending)?;
+    Ok(response
+        .add_attribute("effective_at", pending.effective_at.to_string()))
+}
+
+/// Hatch of a host silent for long enough, with its last contact.
+fn open_hatch(
+    storage: &mut dyn Storage,
+    env: &Env,
+) -> Result<(EscapeHatch, Timestamp), EscapeError> {
+    let hatch =
+        load_hatch(storage, env)?.ok_or(EscapeError::NoEscapeHatch {})?;
+    let last_contact = LAST_HOST_CONTACT.load(storage)?;
+    // Hatches set before the minimum existed wait for it as well
+    let inactivity = hatch.inactivity.max(MIN_INACTIVITY);
+    if env.block.time < last_contact.plus_seconds(inactivity) {
+        return Err(EscapeError::HostStillActive {});
+    }
+    Ok((hatch, last_contact))
+}
+
+/// Staked or otherwise locked funds stay, only the liquid native balance
+/// can be recovered.
+fn withdraw(
+    deps: Deps,
+    env: &Env,
+    recipient: &Addr,
+    response: Response,
+) -> StdResult<Response> {
+    let balance = deps.querier.query_all_balances(&env.contract.address)?;
+    if balance.is_empty() {
+        return Ok(response);
+    }
+    Ok(response.add_message(BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: balance,
+    }))
+}
+
+/// Sent by the backup to withdraw to itself.
+pub fn escape(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, EscapeError> {
+    let (hatch, _) = open_hatch(deps.storage, &env)?;
+    if hatch.backup.as_ref() != Some(&info.sender) {
+        return Err(EscapeError::Unauthorized {});
+    }
+    let response = Response::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "escape")
+        .add_attribute("backup", &info.sender);
+    Ok(withdraw(deps.as_ref(), &env, &info.sender, response)?)
+}
+
+/// Sent by a guardian, the balance goes to `recipient` once enough
+/// guardians approved it since the host went silent.
+pub fn approve_escape(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+) -> Result<Response, EscapeError> {
+    let (hatch, last_contact) = open_hatch(deps.storage, &env)?;
+    let guardians = hatch
+        .guardians
+        .filter(|guardians| guardians.members.contains(&info.sender))
+        .ok_or(EscapeError::Unauthorized {})?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let approval = EscapeApproval {
+        recipient: recipient.clone(),
+        approved_at: env.block.time,
+    };
+    ESCAPE_APPROVALS.save(deps.storage, &info.sender, &approval)?;
+    // Approvals given before the host showed up again are stale
+    let approvals = ESCAPE_APPROVALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref().map_or(true, |(_, approval)| {
+                approval.recipient == recipient
+                    && approval.approved_at >= last_contact
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?
+        .len() as u32;
+    let response = Response::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "approve_escape")
+        .add_attribute("guardian", &info.sender)
+        .add_attribute("recipient", &recipient)
+        .add_attribute("approvals", approvals.to_string());
+    if approvals < guardians.threshold {
+        return Ok(response);
+    }
+    ESCAPE_APPROVALS.clear(deps.storage);
+    Ok(withdraw(deps.as_ref(), &env, &recipient, response)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::EscapeGuardians;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env,
+        mock_info,
+    };
+    use cosmwasm_std::{coins, CosmosMsg};
+
+    fn hatch(backup: Option<&str>, guardians: &[&str]) -> EscapeHatch {
+        EscapeHatch {
+            backup: backup.map(Addr::unchecked),
+            guardians: (!guardians.is_empty()).then(|| EscapeGuardians {
+                members: guardians
+                    .iter()
+                    .copied()
+                    .map(Addr::unchecked)
+                    .collect(),
+                threshold: 2,
+            }),
+            inactivity: MIN_INACTIVITY,
+        }
+    }
+
+    #[test]
+    fn validation() {
+        let deps = mock_dependencies();
+        let check = |hatch: &EscapeHatch| validate(&deps.api, hatch);
+        assert!(check(&hatch(Some("backup"), &[])).is_ok());
+        assert!(check(&hatch(None, &["one", "two"])).is_ok());
+
+        let invalid = [
+            hatch(None, &[]),
+            hatch(None, &["one"]),
+            hatch(None, &["one", "one"]),
+            EscapeHatch { inactivity: 0, ..hatch(Some("backup"), &[]) },
+            EscapeHatch {
+                inactivity: MIN_INACTIVITY - 1,
+                ..hatch(Some("backup"), &[])
+            },
+        ];
+        for hatch in invalid {
+            let res = check(&hatch);
+            assert!(matches!(res, Err(EscapeError::InvalidEscapeHatch(_))));
+        }
+    }
+
+    #[test]
+    fn timelock() {
+        let mut deps = mock_dependencies_with_balance(&coins(10, "token"));
+        let mut env = mock_env();
+        let old = hatch(Some("old"), &[]);
+        init(deps.as_mut(), &env, Some(old.clone())).unwrap();
+
+        env.block.time = env.block.time.plus_seconds(MIN_INACTIVITY);
+        let new = hatch(Some("new"), &[]);
+        set_escape_hatch(deps.as_mut(), env.clone(), Some(new.clone()))
+            .unwrap();
+        let res = escape(deps.as_mut(), env.clone(), mock_info("new", &[]));
+        assert!(matches!(res, Err(EscapeError::Unauthorized {})));
+        escape(deps.as_mut(), env.clone(), mock_info("old", &[])).unwrap();
+
+        // Keeping the active hatch cancels the pending one
+        host_contact(&mut deps.storage, &env).unwrap();
+        set_escape_hatch(deps.as_mut(), env.clone(), Some(new)).unwrap();
+        set_escape_hatch(deps.as_mut(), env.clone(), Some(old.clone()))
+            .unwrap();
+        assert!(!PENDING_ESCAPE_HATCH.exists(&deps.storage));
+
+        let new = hatch(Some("new"), &[]);
+        set_escape_hatch(deps.as_mut(), env.clone(), Some(new)).unwrap();
+        env.block.time = env.block.time.plus_seconds(MIN_INACTIVITY);
+        let res = escape(deps.as_mut(), env.clone(), mock_info("old", &[]));
+        assert!(matches!(res, Err(EscapeError::Unauthorized {})));
+        escape(deps.as_mut(), env.clone(), mock_info("new", &[])).unwrap();
+
+        // Disabling is immediate
+        set_escape_hatch(deps.as_mut(), env.clone(), None).unwrap();
+        let res = escape(deps.as_mut(), env, mock_info("new", &[]));
+        assert!(matches!(res, Err(EscapeError::NoEscapeHatch {})));
+    }
+
+    #[test]
+    fn guardian_quorum() {
+        let mut deps = mock_dependencies_with_balance(&coins(10, "token"));
+        let mut env = mock_env();
+        let hatch = hatch(None, &["one", "two", "three"]);
+        init(deps.as_mut(), &env, Some(hatch)).unwrap();
+        let approve =
+            |deps: DepsMut, env: &Env, guardian, recipient: &str| {
+                let info = mock_info(guardian, &[]);
+                approve_escape(deps, env.clone(), info, recipient.to_string())
+            };
+
+        let res = approve(deps.as_mut(), &env, "one", "heir");
+        assert!(matches!(res, Err(EscapeError::HostStillActive {})));
+        env.block.time = env.block.time.plus_seconds(MIN_INACTIVITY);
+        let res = approve(deps.as_mut(), &env, "stranger", "heir");
+        assert!(matches!(res, Err(EscapeError::Unauthorized {})));
+        let res = escape(deps.as_mut(), env.clone(), mock_info("one", &[]));
+        assert!(matches!(res, Err(EscapeError::Unauthorized {})));
+
+        // Approvals from before the host showed up again do not count
+        let res = approve(deps.as_mut(), &env, "one", "heir").unwrap();
+        assert!(res.messages.is_empty());
+        env.block.time = env.block.time.plus_seconds(1);
+        host_contact(&mut deps.storage, &env).unwrap();
+        env.block.time = env.block.time.plus_seconds(MIN_INACTIVITY);
+        let res = approve(deps.as_mut(), &env, "two", "heir").unwrap();
+        assert!(res.messages.is_empty());
+
+        // Only approvals for the same recipient add up
+        let res = approve(deps.as_mut(), &env, "three", "thief").unwrap();
+        assert!(res.messages.is_empty());
+        let res = approve(deps.as_mut(), &env, "one", "heir").unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "heir".to_string(),
+                amount: coins(10, "token"),
+            })
+        );
+        assert!(ESCAPE_APPROVALS.is_empty(&deps.storage));
+    }
+}
@@ -0,0 +1,22 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Addr;
+
+/// Lets the backup or a quorum of guardians withdraw the native balance
+/// once the host has been silent for `inactivity` seconds, e.g. because
+/// the channel closed or the host chain halted.
+#[cw_serde]
+pub struct EscapeHatch {
+    /// Withdraws to itself with `Escape`.
+    pub backup: Option<Addr>,
+    /// Agree on a recipient with `ApproveEscape`.
+    pub guardians: Option<EscapeGuardians>,
+    /// At least [`crate::MIN_INACTIVITY`].
+    pub inactivity: u64,
+}
+
+#[cw_serde]
+pub struct EscapeGuardians {
+    pub members: Vec<Addr>,
+    /// Approvals for the same recipient needed to withdraw.
+    pub threshold: u32,
+}
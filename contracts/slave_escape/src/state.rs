@@ -0,0 +1,28 @@
+use cosmwasm_std::{Addr, Timestamp};
+use cw_storage_plus::{Item, Map};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::msg::EscapeHatch;
+
+/// Hatch set by the host, replaces the active one once effective.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingEscapeHatch {
+    pub hatch: EscapeHatch,
+    pub effective_at: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscapeApproval {
+    pub recipient: Addr,
+    pub approved_at: Timestamp,
+}
+
+pub const ESCAPE_HATCH: Item<EscapeHatch> = Item::new("escape_hatch");
+pub const PENDING_ESCAPE_HATCH: Item<PendingEscapeHatch> =
+    Item::new("pending_escape_hatch");
+/// Last time the host executed anything on the slave.
+pub const LAST_HOST_CONTACT: Item<Timestamp> = Item::new("last_host_contact");
+/// guardian -> recipient approved by the guardian, cleared with the hatch
+pub const ESCAPE_APPROVALS: Map<&Addr, EscapeApproval> =
+    Map::new("escape_approvals");
@@ -0,0 +1,11 @@
+use cosmwasm_schema::write_api;
+
+use slave_ibc::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg};
+
+fn main() {
+    write_api! {
+        instantiate: InstantiateMsg,
+        execute: ExecuteMsg,
+        migrate: MigrateMsg,
+    }
+}
@@ -0,0 +1,481 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::{
+    entry_point, from_json, to_json_binary, CosmosMsg, DepsMut, Env, IbcMsg,
+    IbcTimeout, MessageInfo, Reply, Response, StdError, SubMsg, SubMsgResult,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw_utils::parse_execute_response_data;
+use semver::Version;
+
+use crate::error::ContractError;
+use crate::msg::{
+    BatchFailure, BatchMode, BatchResult, ExecuteMsg, HostPacket,
+    InstantiateMsg, MigrateMsg, SlaveAck,
+};
+use crate::state::{State, BATCH_RESULT, CHANNEL, CONFIG, STATE};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:slave_ibc";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Reply id of a received packet batch, out of range of batch indices.
+pub const REPLY_RECEIVE_PACKET: u64 = u64::MAX;
+/// Packets not relayed within an hour fail.
+pub const PACKET_LIFETIME: u64 = 60 * 60;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    let state = State { host: msg.host.clone() };
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    STATE.save(deps.storage, &state)?;
+    slave_escape::init(deps.branch(), &env, msg.escape_hatch)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "instantiate")
+        .add_attribute("slave_address", env.contract.address)
+        .add_attribute("host", msg.host))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    deps: DepsMut,
+    env: Env,
+    _msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+    let parse = |version: &str| {
+        Version::parse(version)
+            .map_err(|e| ContractError::InvalidVersion(e.to_string()))
+    };
+    if parse(&stored.version)? > parse(CONTRACT_VERSION)? {
+        return Err(ContractError::CannotDowngrade {
+            previous_version: stored.version,
+        });
+    }
+
+    if parse(&stored.version)? < Version::new(0, 2, 0) {
+        // Inactivity before the upgrade is unknown, count from now
+        slave_escape::host_contact(deps.storage, &env)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("previous_version", stored.version)
+        .add_attribute("version", CONTRACT_VERSION))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    // Host is on another chain, its messages only arrive through packets
+    let from_host = info.sender == env.contract.address;
+    match msg {
+        ExecuteMsg::ExecuteBatch { msgs, mode } => {
+            if !from_host {
+                return Err(ContractError::Unauthorized {});
+            }
+            execute_batch(deps, msgs, mode)
+        }
+        ExecuteMsg::ApproveRecovery { target } => {
+            approve_recovery(deps, env, info, target)
+        }
+        ExecuteMsg::SetEscapeHatch { hatch } => {
+            if !from_host {
+                return Err(ContractError::Unauthorized {});
+            }
+            Ok(slave_escape::set_escape_hatch(deps, env, hatch)?)
+        }
+        ExecuteMsg::Escape {} => Ok(slave_escape::escape(deps, env, info)?),
+        ExecuteMsg::ApproveEscape { recipient } => {
+            Ok(slave_escape::approve_escape(deps, env, info, recipient)?)
+        }
+    }
+}
+
+/// The host only counts the approval if the sender is one of its
+/// guardians on this chain, others are rejected early once the config is
+/// synced.
+fn approve_recovery(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    target: String,
+) -> Result<Response, ContractError> {
+    if let Some(config) = CONFIG.may_load(deps.storage)? {
+        if !config.guardians.contains(&info.sender.to_string()) {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
+    let channel_id = CHANNEL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::ChannelNotOpen {})?;
+    let packet = HostPacket::ApproveRecovery {
+        guardian: info.sender.to_string(),
+        target: target.clone(),
+    };
+    Ok(Response::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "approve_recovery")
+        .add_attribute("guardian", info.sender)
+        .add_attribute("target", target)
+        .add_message(IbcMsg::SendPacket {
+            channel_id,
+            data: to_json_binary(&packet)?,
+            timeout: IbcTimeout::with_timestamp(
+                env.block.time.plus_seconds(PACKET_LIFETIME),
+            ),
+        }))
+}
+
+fn execute_batch(
+    deps: DepsMut,
+    msgs: Vec<CosmosMsg>,
+    mode: BatchMode,
+) -> Result<Response, ContractError> {
+    if msgs.iter().any(|msg| matches!(msg, CosmosMsg::Custom(_))) {
+        return Err(ContractError::NotImplemented(
+            "Custom messages not supported yet.".to_string(),
+        ));
+    }
+
+    let result = BatchResult { total: msgs.len() as u32, failed: vec![] };
+    let response = Response::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "execute_batch")
+        .add_attribute("total", result.total.to_string())
+        .set_data(to_json_binary(&result)?);
+    match mode {
+        BatchMode::Atomic => {
+            Ok(response.add_attribute("mode", "atomic").add_messages(msgs))
+        }
+        BatchMode::BestEffort => {
+            BATCH_RESULT.save(deps.storage, &result)?;
+            // Reply id is the message index within the batch
+            let sub_msgs = msgs
+                .into_iter()
+                .enumerate()
+                .map(|(i, msg)| SubMsg::reply_on_error(msg, i as u64));
+            Ok(response
+                .add_attribute("mode", "best_effort")
+                .add_submessages(sub_msgs))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(
+    deps: DepsMut,
+    _env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    if msg.id == REPLY_RECEIVE_PACKET {
+        return packet_executed(msg.result);
+    }
+    let SubMsgResult::Err(error) = msg.result else {
+        return Err(ContractError::UnknownReplyID(msg.id));
+    };
+    let mut result = BATCH_RESULT.load(deps.storage)?;
+    result
+        .failed
+        .push(BatchFailure { index: msg.id as u32, error: error.clone() });
+    BATCH_RESULT.save(deps.storage, &result)?;
+
+    // Every reply overrides the data, so always report all failures so far
+    Ok(Response::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "batch_message_failed")
+        .add_attribute("index", msg.id.to_string())
+        .add_attribute("error", error)
+        .set_data(to_json_binary(&result)?))
+}
+
+/// Data set here replaces the acknowledgement of the received packet.
+fn packet_executed(result: SubMsgResult) -> Result<Response, ContractError> {
+    let ack = match result {
+        SubMsgResult::Ok(res) => {
+            let data = match res.data {
+                Some(data) => {
+                    parse_execute_response_data(&data)
+                        .map_err(|e| StdError::generic_err(e.to_string()))?
+                        .data
+                }
+                None => None,
+            };
+            // Our own `ExecuteBatch` always reports the batch result
+            let result: Option<BatchResult> =
+                data.as_ref().map(from_json).transpose()?;
+            SlaveAck::Result(result.unwrap_or_default())
+        }
+        SubMsgResult::Err(error) => SlaveAck::Error(error),
+    };
+    Ok(Response::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "packet_executed")
+        .add_attribute(
+            "success",
+            matches!(ack, SlaveAck::Result(_)).to_string(),
+        )
+        .set_data(to_json_binary(&ack)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ibc::{
+        ibc_channel_connect, ibc_channel_open, ibc_packet_receive, IBC_ORDER,
+        IBC_VERSION,
+    };
+    use crate::msg::{
+        EscapeGuardians, EscapeHatch, SlaveConfig, SlavePacket,
+    };
+    use crate::state::CHANNEL;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env,
+        mock_ibc_channel_connect_ack, mock_ibc_channel_open_try,
+        mock_ibc_packet_recv, mock_info,
+    };
+    use cosmwasm_std::{
+        coins, Addr, BankMsg, IbcChannelOpenMsg, IbcOrder, StdAck,
+        SubMsgResponse,
+    };
+    use slave_escape::{EscapeError, MIN_INACTIVITY};
+
+    #[test]
+    fn packet_execution() {
+        let mut deps = mock_dependencies();
+
+        let msg =
+            InstantiateMsg { host: "host".to_string(), escape_hatch: None };
+        let info = mock_info("deployer", &[]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let open = mock_ibc_channel_open_try(
+            "channel-1",
+            IbcOrder::Ordered,
+            IBC_VERSION,
+        );
+        let res = ibc_channel_open(deps.as_mut(), mock_env(), open);
+        assert!(matches!(res, Err(ContractError::InvalidIbcOrder {})));
+        let open =
+            mock_ibc_channel_open_try("channel-1", IBC_ORDER, "ics20-1");
+        let res = ibc_channel_open(deps.as_mut(), mock_env(), open);
+        assert!(matches!(res, Err(ContractError::InvalidIbcVersion { .. })));
+        // Mock counterparty is not our host
+        let open =
+            mock_ibc_channel_open_try("channel-1", IBC_ORDER, IBC_VERSION);
+        let res = ibc_channel_open(deps.as_mut(), mock_env(), open.clone());
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+
+        let mut channel = open.channel().clone();
+        channel.counterparty_endpoint.port_id = "wasm.host".to_string();
+        let open = IbcChannelOpenMsg::new_try(channel, IBC_VERSION);
+        ibc_channel_open(deps.as_mut(), mock_env(), open).unwrap();
+        let connect =
+            mock_ibc_channel_connect_ack("channel-1", IBC_ORDER, IBC_VERSION);
+        ibc_channel_connect(deps.as_mut(), mock_env(), connect).unwrap();
+        assert_eq!(CHANNEL.load(&deps.storage).unwrap(), "channel-1");
+
+        let send: CosmosMsg = BankMsg::Send {
+            to_address: "friend".to_string(),
+            amount: coins(5, "token"),
+        }
+        .into();
+        let packet = SlavePacket::ExecuteBatch {
+            tx_id: 3,
+            msgs: vec![send.clone()],
+            mode: BatchMode::Atomic,
+        };
+        let recv = mock_ibc_packet_recv("channel-2", &packet).unwrap();
+        let res =
+            ibc_packet_receive(deps.as_mut(), mock_env(), recv).unwrap();
+        let ack: SlaveAck = from_json(res.acknowledgement).unwrap();
+        assert_eq!(ack, SlaveAck::Error("Unauthorized".to_string()));
+
+        let recv = mock_ibc_packet_recv("channel-1", &packet).unwrap();
+        let res =
+            ibc_packet_receive(deps.as_mut(), mock_env(), recv).unwrap();
+        assert_eq!(res.messages[0].id, REPLY_RECEIVE_PACKET);
+        let batch = ExecuteMsg::ExecuteBatch {
+            msgs: vec![send],
+            mode: BatchMode::Atomic,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            batch.clone(),
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+        let self_info = mock_info(mock_env().contract.address.as_str(), &[]);
+        let res = execute(deps.as_mut(), mock_env(), self_info, batch);
+        let data = res.unwrap().data.unwrap();
+
+        // MsgExecuteContractResponse { data } protobuf envelope
+        let mut envelope = vec![0x0a, data.len() as u8];
+        envelope.extend_from_slice(data.as_slice());
+        let reply_msg = Reply {
+            id: REPLY_RECEIVE_PACKET,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(envelope.into()),
+            }),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        let ack: SlaveAck = from_json(res.data.unwrap()).unwrap();
+        assert_eq!(
+            ack,
+            SlaveAck::Result(BatchResult { total: 1, failed: vec![] })
+        );
+
+        let reply_msg = Reply {
+            id: REPLY_RECEIVE_PACKET,
+            result: SubMsgResult::Err("insufficient funds".to_string()),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        let ack: SlaveAck = from_json(res.data.unwrap()).unwrap();
+        assert_eq!(ack, SlaveAck::Error("insufficient funds".to_string()));
+    }
+
+    #[test]
+    fn recovery_approval() {
+        let mut deps = mock_dependencies();
+
+        let msg =
+            InstantiateMsg { host: "host".to_string(), escape_hatch: None };
+        let info = mock_info("deployer", &[]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::ApproveRecovery { target: "new".to_string() };
+        let guardian = mock_info("guardian", &[]);
+        let res =
+            execute(deps.as_mut(), mock_env(), guardian.clone(), msg.clone());
+        assert!(matches!(res, Err(ContractError::ChannelNotOpen {})));
+
+        CHANNEL.save(&mut deps.storage, &"channel-1".to_string()).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), guardian, msg).unwrap();
+        let CosmosMsg::Ibc(IbcMsg::SendPacket { channel_id, data, .. }) =
+            &res.messages[0].msg
+        else {
+            panic!("Approval not sent to the host");
+        };
+        assert_eq!(channel_id, "channel-1");
+        let packet: HostPacket = from_json(data).unwrap();
+        assert_eq!(
+            packet,
+            HostPacket::ApproveRecovery {
+                guardian: "guardian".to_string(),
+                target: "new".to_string(),
+            }
+        );
+
+        let sync = |version: u64, guardians: &[&str]| {
+            let packet = SlavePacket::SyncConfig {
+                version,
+                config: SlaveConfig {
+                    owner: "owner".to_string(),
+                    guardians: guardians
+                        .iter()
+                        .map(|g| g.to_string())
+                        .collect(),
+                },
+            };
+            mock_ibc_packet_recv("channel-1", &packet).unwrap()
+        };
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), sync(2, &[]))
+            .unwrap();
+        let ack: StdAck = from_json(res.acknowledgement).unwrap();
+        assert!(ack.is_success());
+        // Late older config is ignored
+        let recv = sync(1, &["guardian"]);
+        ibc_packet_receive(deps.as_mut(), mock_env(), recv).unwrap();
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().guardians.len(), 0);
+
+        let msg = ExecuteMsg::ApproveRecovery { target: "new".to_string() };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("guardian", &[]),
+            msg,
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+    }
+
+    #[test]
+    fn escape_hatch() {
+        let mut deps = mock_dependencies_with_balance(&coins(10, "token"));
+
+        let msg = InstantiateMsg {
+            host: "host".to_string(),
+            escape_hatch: Some(EscapeHatch {
+                backup: None,
+                guardians: Some(EscapeGuardians {
+                    members: vec![
+                        Addr::unchecked("one"),
+                        Addr::unchecked("two"),
+                    ],
+                    threshold: 2,
+                }),
+                inactivity: MIN_INACTIVITY,
+            }),
+        };
+        let info = mock_info("deployer", &[]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        CHANNEL.save(&mut deps.storage, &"channel-1".to_string()).unwrap();
+
+        let msg = ExecuteMsg::SetEscapeHatch { hatch: None };
+        let info = mock_info("one", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+        let approve = |guardian: &str| {
+            let msg =
+                ExecuteMsg::ApproveEscape { recipient: "heir".to_string() };
+            (mock_info(guardian, &[]), msg)
+        };
+
+        // Any packet from the host restarts the inactivity period
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(500);
+        let packet = SlavePacket::ExecuteBatch {
+            tx_id: 1,
+            msgs: vec![],
+            mode: BatchMode::Atomic,
+        };
+        let recv = mock_ibc_packet_recv("channel-1", &packet).unwrap();
+        ibc_packet_receive(deps.as_mut(), env.clone(), recv).unwrap();
+        env.block.time = env.block.time.plus_seconds(MIN_INACTIVITY - 1);
+        let (info, msg) = approve("one");
+        let res = execute(deps.as_mut(), env.clone(), info, msg);
+        assert!(matches!(
+            res,
+            Err(ContractError::Escape(EscapeError::HostStillActive {}))
+        ));
+
+        env.block.time = env.block.time.plus_seconds(1);
+        let (info, msg) = approve("one");
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert!(res.messages.is_empty());
+        let (info, msg) = approve("two");
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "heir".to_string(),
+                amount: coins(10, "token"),
+            })
+        );
+    }
+}
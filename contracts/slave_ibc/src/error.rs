@@ -0,0 +1,42 @@
+use cosmwasm_std::StdError;
+use slave_escape::EscapeError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    NotImplemented(String),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Unknown reply ID: {0}")]
+    UnknownReplyID(u64),
+
+    #[error("{0}")]
+    Escape(#[from] EscapeError),
+
+    #[error("Only unordered channels are supported.")]
+    InvalidIbcOrder {},
+
+    #[error("Invalid IBC channel version: {version}")]
+    InvalidIbcVersion { version: String },
+
+    #[error("Slave is already connected to its host.")]
+    ChannelAlreadyOpen {},
+
+    #[error("Slave is not connected to its host.")]
+    ChannelNotOpen {},
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from newer version {previous_version}")]
+    CannotDowngrade { previous_version: String },
+
+    #[error("Invalid contract version: {0}")]
+    InvalidVersion(String),
+}
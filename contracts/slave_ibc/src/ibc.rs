@@ -0,0 +1,191 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_json, to_json_binary, Binary, DepsMut, Env, Ibc3ChannelOpenResponse,
+    IbcBasicResponse, IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg,
+    IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, Never,
+    StdAck, SubMsg, WasmMsg,
+};
+
+use crate::contract::REPLY_RECEIVE_PACKET;
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, SlaveAck, SlavePacket};
+use crate::state::{CHANNEL, CONFIG, CONFIG_VERSION, STATE};
+
+pub const IBC_VERSION: &str = "ward-1";
+pub const IBC_ORDER: IbcOrder = IbcOrder::Unordered;
+
+fn validate_channel(
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> Result<(), ContractError> {
+    if channel.order != IBC_ORDER {
+        return Err(ContractError::InvalidIbcOrder {});
+    }
+    for version in
+        std::iter::once(channel.version.as_str()).chain(counterparty_version)
+    {
+        if version != IBC_VERSION {
+            return Err(ContractError::InvalidIbcVersion {
+                version: version.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Only a single channel to the wasm port of our host is accepted.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<IbcChannelOpenResponse, ContractError> {
+    let channel = msg.channel();
+    validate_channel(channel, msg.counterparty_version())?;
+    if CHANNEL.exists(deps.storage) {
+        return Err(ContractError::ChannelAlreadyOpen {});
+    }
+    let state = STATE.load(deps.storage)?;
+    if channel.counterparty_endpoint.port_id != format!("wasm.{}", state.host)
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(Some(Ibc3ChannelOpenResponse { version: IBC_VERSION.to_string() }))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel = msg.channel();
+    validate_channel(channel, msg.counterparty_version())?;
+    if CHANNEL.exists(deps.storage) {
+        return Err(ContractError::ChannelAlreadyOpen {});
+    }
+    CHANNEL.save(deps.storage, &channel.endpoint.channel_id)?;
+    Ok(IbcBasicResponse::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "ibc_channel_connect")
+        .add_attribute("channel_id", &channel.endpoint.channel_id))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel_id = &msg.channel().endpoint.channel_id;
+    if CHANNEL.may_load(deps.storage)?.as_ref() == Some(channel_id) {
+        CHANNEL.remove(deps.storage);
+    }
+    Ok(IbcBasicResponse::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "ibc_channel_close")
+        .add_attribute("channel_id", channel_id))
+}
+
+/// Errors are returned as acknowledgements, so that the packet is never
+/// stuck on the relayer.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, Never> {
+    receive(deps, env, msg).or_else(|err| {
+        Ok(IbcReceiveResponse::new()
+            .add_attribute("contract", "slave")
+            .add_attribute("method", "ibc_packet_receive")
+            .add_attribute("error", err.to_string())
+            .set_ack(ack_error(err.to_string())))
+    })
+}
+
+fn ack_error(error: String) -> Binary {
+    to_json_binary(&SlaveAck::Error(error)).unwrap()
+}
+
+fn receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let channel_id = CHANNEL.may_load(deps.storage)?;
+    if channel_id.as_ref() != Some(&msg.packet.dest.channel_id) {
+        return Err(ContractError::Unauthorized {});
+    }
+    slave_escape::host_contact(deps.storage, &env)?;
+    match from_json(&msg.packet.data)? {
+        SlavePacket::ExecuteBatch { tx_id, msgs, mode } => {
+            // Run through ourselves, so that an atomic batch is reverted
+            // as a whole and reported in the acknowledgement from reply
+            let batch = WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::ExecuteBatch {
+                    msgs,
+                    mode,
+                })?,
+                funds: vec![],
+            };
+            Ok(IbcReceiveResponse::new()
+                .add_attribute("contract", "slave")
+                .add_attribute("method", "ibc_packet_receive")
+                .add_attribute("tx_id", tx_id.to_string())
+                // Replaced with the outcome in reply
+                .set_ack(ack_error("No reply.".to_string()))
+                .add_submessage(SubMsg::reply_always(
+                    batch,
+                    REPLY_RECEIVE_PACKET,
+                )))
+        }
+        SlavePacket::SyncConfig { version, config } => {
+            // Older configs arriving late are acknowledged, but ignored
+            let current = CONFIG_VERSION.may_load(deps.storage)?;
+            let updated = current < Some(version);
+            if updated {
+                CONFIG.save(deps.storage, &config)?;
+                CONFIG_VERSION.save(deps.storage, &version)?;
+            }
+            Ok(IbcReceiveResponse::new()
+                .add_attribute("contract", "slave")
+                .add_attribute("method", "ibc_packet_receive")
+                .add_attribute("config_version", version.to_string())
+                .add_attribute("updated", updated.to_string())
+                .set_ack(StdAck::success(Binary::default())))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    // Approvals are final on the host, only report the outcome
+    let response = IbcBasicResponse::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "ibc_packet_ack");
+    match from_json(&msg.acknowledgement.data)? {
+        StdAck::Success(_) => Ok(response.add_attribute("success", "true")),
+        StdAck::Error(error) => Ok(response
+            .add_attribute("success", "false")
+            .add_attribute("error", error)),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    _deps: DepsMut,
+    _env: Env,
+    _msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    Ok(IbcBasicResponse::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "ibc_packet_timeout"))
+}
@@ -0,0 +1,92 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::CosmosMsg;
+pub use slave_escape::msg::{EscapeGuardians, EscapeHatch};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address of the controlling host on its own chain.
+    pub host: String,
+    pub escape_hatch: Option<EscapeHatch>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Only used by the slave itself to run received batches atomically.
+    ExecuteBatch { msgs: Vec<CosmosMsg>, mode: BatchMode },
+    /// Approve the social recovery of the host to `target` as a guardian
+    /// living on this chain.
+    ApproveRecovery { target: String },
+    /// Sent by the host within a batch, `None` disables the escape hatch.
+    /// A new hatch only takes effect after
+    /// [`slave_escape::ESCAPE_HATCH_DELAY`].
+    SetEscapeHatch { hatch: Option<EscapeHatch> },
+    /// Sent by the backup to withdraw the balance once no packet arrived
+    /// from the host for the hatch inactivity.
+    Escape {},
+    /// Sent by an escape guardian, the balance goes to `recipient` once
+    /// enough guardians agree.
+    ApproveEscape { recipient: String },
+}
+
+#[cw_serde]
+pub enum BatchMode {
+    /// Any failure reverts the whole batch.
+    Atomic,
+    /// Failed messages are reverted and reported, the rest still runs.
+    BestEffort,
+}
+
+/// Set as response data of `ExecuteBatch`.
+#[cw_serde]
+#[derive(Default)]
+pub struct BatchResult {
+    pub total: u32,
+    pub failed: Vec<BatchFailure>,
+}
+
+#[cw_serde]
+pub struct BatchFailure {
+    pub index: u32,
+    pub error: String,
+}
+
+/// Packets sent by the host over the slave channel.
+#[cw_serde]
+pub enum SlavePacket {
+    ExecuteBatch {
+        tx_id: u64,
+        msgs: Vec<CosmosMsg>,
+        mode: BatchMode,
+    },
+    /// Acknowledged with a [`cosmwasm_std::StdAck`].
+    SyncConfig {
+        version: u64,
+        config: SlaveConfig,
+    },
+}
+
+/// Wallet config mirrored from the host.
+#[cw_serde]
+pub struct SlaveConfig {
+    /// Owner of the wallet on the host chain.
+    pub owner: String,
+    /// Guardians on this chain allowed to approve recovery.
+    pub guardians: Vec<String>,
+}
+
+/// Packets sent to the host, acknowledged with a
+/// [`cosmwasm_std::StdAck`].
+#[cw_serde]
+pub enum HostPacket {
+    ApproveRecovery { guardian: String, target: String },
+}
+
+/// Acknowledgement written for every [`SlavePacket::ExecuteBatch`].
+#[cw_serde]
+pub enum SlaveAck {
+    Result(BatchResult),
+    Error(String),
+}
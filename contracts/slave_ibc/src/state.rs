@@ -0,0 +1,22 @@
+use cw_storage_plus::Item;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::msg::{BatchResult, SlaveConfig};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct State {
+    /// Host contract address on the controller chain, cannot be validated
+    /// here.
+    pub host: String,
+}
+
+pub const STATE: Item<State> = Item::new("state");
+/// Channel to the host, only one may be open at a time.
+pub const CHANNEL: Item<String> = Item::new("channel");
+/// Latest wallet config pushed by the host.
+pub const CONFIG: Item<SlaveConfig> = Item::new("config");
+/// Version of `CONFIG`, packets may arrive out of order.
+pub const CONFIG_VERSION: Item<u64> = Item::new("config_version");
+/// Outcome of the best-effort batch currently being executed.
+pub const BATCH_RESULT: Item<BatchResult> = Item::new("batch_result");
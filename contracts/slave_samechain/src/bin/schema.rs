@@ -1,11 +1,11 @@
 use cosmwasm_schema::write_api;
 
-use host::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use slave_samechain::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg};
 
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
-        query: QueryMsg,
+        migrate: MigrateMsg,
     }
 }
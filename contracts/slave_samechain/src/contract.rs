@@ -1,13 +1,17 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::{
-    entry_point, to_binary, CosmosMsg, DepsMut, Env, MessageInfo, Response,
-    WasmMsg,
+    entry_point, to_json_binary, CosmosMsg, DepsMut, Env, MessageInfo, Reply,
+    Response, Storage, SubMsg, SubMsgResult, WasmMsg,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use semver::Version;
 
 use crate::error::ContractError;
-use crate::msg::{HostRegisterSlaveMsg, InstantiateMsg};
-use crate::state::{State, STATE};
+use crate::msg::{
+    BatchFailure, BatchMode, BatchResult, ExecuteMsg, HostRegisterSlaveMsg,
+    InstantiateMsg, MigrateMsg,
+};
+use crate::state::{State, BATCH_RESULT, STATE};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:slave_samechain";
@@ -15,7 +19,7 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
@@ -23,6 +27,7 @@ pub fn instantiate(
     let state = State { owner: msg.owner.clone() };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
+    slave_escape::init(deps.branch(), &env, msg.escape_hatch)?;
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -30,7 +35,7 @@ pub fn instantiate(
         .add_attribute("owner", msg.owner.clone())
         .add_message(WasmMsg::Execute {
             contract_addr: msg.owner.to_string(),
-            msg: to_binary(&HostRegisterSlaveMsg::RegisterSlave {
+            msg: to_json_binary(&HostRegisterSlaveMsg::RegisterSlave {
                 chain: msg.chain,
                 addr: env.contract.address.clone(),
             })?,
@@ -38,6 +43,40 @@ pub fn instantiate(
         }))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    deps: DepsMut,
+    env: Env,
+    _msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+    let parse = |version: &str| {
+        Version::parse(version)
+            .map_err(|e| ContractError::InvalidVersion(e.to_string()))
+    };
+    if parse(&stored.version)? > parse(CONTRACT_VERSION)? {
+        return Err(ContractError::CannotDowngrade {
+            previous_version: stored.version,
+        });
+    }
+
+    // 0.2.0 only changed the execute interface, state is compatible
+    if parse(&stored.version)? < Version::new(0, 3, 0) {
+        // Inactivity before the upgrade is unknown, count from now
+        slave_escape::host_contact(deps.storage, &env)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("previous_version", stored.version)
+        .add_attribute("version", CONTRACT_VERSION))
+}
+
 macro_rules! require_owner {
     ($info:ident, $state:ident) => {
         if $info.sender != $state.owner {
@@ -49,21 +88,276 @@ macro_rules! require_owner {
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    msg: CosmosMsg,
+    msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
+    match msg {
+        ExecuteMsg::ExecuteBatch { msgs, mode } => {
+            host_contact(deps.storage, &env, &info)?;
+            execute_batch(deps, msgs, mode)
+        }
+        ExecuteMsg::SetEscapeHatch { hatch } => {
+            // The host only sends batches, which run as the slave itself
+            if info.sender != env.contract.address {
+                return Err(ContractError::Unauthorized {});
+            }
+            Ok(slave_escape::set_escape_hatch(deps, env, hatch)?)
+        }
+        ExecuteMsg::Escape {} => Ok(slave_escape::escape(deps, env, info)?),
+        ExecuteMsg::ApproveEscape { recipient } => {
+            Ok(slave_escape::approve_escape(deps, env, info, recipient)?)
+        }
+    }
+}
+
+/// Only the host may proceed, which also proves it is still alive.
+fn host_contact(
+    storage: &mut dyn Storage,
+    env: &Env,
+    info: &MessageInfo,
+) -> Result<(), ContractError> {
+    let state = STATE.load(storage)?;
     require_owner!(info, state);
+    slave_escape::host_contact(storage, env)?;
+    Ok(())
+}
 
-    if let CosmosMsg::Custom(_) = msg {
+fn execute_batch(
+    deps: DepsMut,
+    msgs: Vec<CosmosMsg>,
+    mode: BatchMode,
+) -> Result<Response, ContractError> {
+    if msgs.iter().any(|msg| matches!(msg, CosmosMsg::Custom(_))) {
         return Err(ContractError::NotImplemented(
             "Custom messages not supported yet.".to_string(),
         ));
     }
 
+    let result = BatchResult { total: msgs.len() as u32, failed: vec![] };
+    let response = Response::new()
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "execute_batch")
+        .add_attribute("total", result.total.to_string())
+        .set_data(to_json_binary(&result)?);
+    match mode {
+        BatchMode::Atomic => {
+            Ok(response.add_attribute("mode", "atomic").add_messages(msgs))
+        }
+        BatchMode::BestEffort => {
+            BATCH_RESULT.save(deps.storage, &result)?;
+            // Reply id is the message index within the batch
+            let sub_msgs = msgs
+                .into_iter()
+                .enumerate()
+                .map(|(i, msg)| SubMsg::reply_on_error(msg, i as u64));
+            Ok(response
+                .add_attribute("mode", "best_effort")
+                .add_submessages(sub_msgs))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(
+    deps: DepsMut,
+    _env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let SubMsgResult::Err(error) = msg.result else {
+        return Err(ContractError::UnknownReplyID(msg.id));
+    };
+    let mut result = BATCH_RESULT.load(deps.storage)?;
+    result
+        .failed
+        .push(BatchFailure { index: msg.id as u32, error: error.clone() });
+    BATCH_RESULT.save(deps.storage, &result)?;
+
+    // Every reply overrides the data, so always report all failures so far
     Ok(Response::new()
-        .add_attribute("contract", "host")
-        .add_attribute("method", "execute_samechain_transaction")
-        .add_message(msg))
+        .add_attribute("contract", "slave")
+        .add_attribute("method", "batch_message_failed")
+        .add_attribute("index", msg.id.to_string())
+        .add_attribute("error", error)
+        .set_data(to_json_binary(&result)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::EscapeHatch;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env,
+        mock_info,
+    };
+    use cosmwasm_std::{coins, from_json, Addr, BankMsg, ReplyOn};
+    use slave_escape::{EscapeError, ESCAPE_HATCH_DELAY, MIN_INACTIVITY};
+
+    #[test]
+    fn best_effort_batch() {
+        let mut deps = mock_dependencies();
+
+        let info = mock_info("host", &[]);
+        let msg = InstantiateMsg {
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+            escape_hatch: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let send: CosmosMsg = BankMsg::Send {
+            to_address: "friend".to_string(),
+            amount: coins(5, "token"),
+        }
+        .into();
+        let msg = ExecuteMsg::ExecuteBatch {
+            msgs: vec![send.clone(), send.clone()],
+            mode: BatchMode::BestEffort,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            msg.clone(),
+        );
+        assert!(matches!(res, Err(ContractError::Unauthorized {})));
+
+        let res =
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(res.messages[1].id, 1);
+        assert_eq!(res.messages[1].reply_on, ReplyOn::Error);
+
+        let reply_msg = Reply {
+            id: 1,
+            result: SubMsgResult::Err("insufficient funds".to_string()),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        let result: BatchResult = from_json(res.data.unwrap()).unwrap();
+        assert_eq!(result.total, 2);
+        assert_eq!(
+            result.failed,
+            vec![BatchFailure {
+                index: 1,
+                error: "insufficient funds".to_string()
+            }]
+        );
+
+        let msg = ExecuteMsg::ExecuteBatch {
+            msgs: vec![send],
+            mode: BatchMode::Atomic,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(res.messages[0].reply_on, ReplyOn::Never);
+        let result: BatchResult = from_json(res.data.unwrap()).unwrap();
+        assert!(result.failed.is_empty());
+    }
+
+    #[test]
+    fn escape_hatch() {
+        let mut deps = mock_dependencies_with_balance(&coins(10, "token"));
+
+        let info = mock_info("host", &[]);
+        let backup = mock_info("backup", &[]);
+        let hatch = |backup: &str| EscapeHatch {
+            backup: Some(Addr::unchecked(backup)),
+            guardians: None,
+            inactivity: MIN_INACTIVITY,
+        };
+        let msg = InstantiateMsg {
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+            escape_hatch: Some(EscapeHatch {
+                inactivity: 0,
+                ..hatch("backup")
+            }),
+        };
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg);
+        assert!(matches!(
+            res,
+            Err(ContractError::Escape(EscapeError::InvalidEscapeHatch(_)))
+        ));
+        let msg = InstantiateMsg {
+            owner: info.sender.clone(),
+            chain: "foo-1".to_string(),
+            escape_hatch: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            backup.clone(),
+            ExecuteMsg::Escape {},
+        );
+        assert!(matches!(
+            res,
+            Err(ContractError::Escape(EscapeError::NoEscapeHatch {}))
+        ));
+
+        let msg = ExecuteMsg::SetEscapeHatch { hatch: Some(hatch("backup")) };
+        for sender in [&backup, &info] {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                sender.clone(),
+                msg.clone(),
+            );
+            assert!(matches!(res, Err(ContractError::Unauthorized {})));
+        }
+
+        // The host wraps the call in a batch, executed by the slave itself
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(500);
+        let call: CosmosMsg = WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&msg).unwrap(),
+            funds: vec![],
+        }
+        .into();
+        let batch = ExecuteMsg::ExecuteBatch {
+            msgs: vec![call.clone()],
+            mode: BatchMode::Atomic,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, batch).unwrap();
+        assert_eq!(res.messages[0].msg, call);
+        let slave = mock_info(env.contract.address.as_str(), &[]);
+        let res = execute(deps.as_mut(), env.clone(), slave, msg).unwrap();
+        let effective_at = env.block.time.plus_seconds(ESCAPE_HATCH_DELAY);
+        assert!(res.attributes.iter().any(|attr| attr.key == "effective_at"
+            && attr.value == effective_at.to_string()));
+
+        // Host contact restarted the inactivity period
+        env.block.time = env.block.time.plus_seconds(MIN_INACTIVITY - 1);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            backup.clone(),
+            ExecuteMsg::Escape {},
+        );
+        assert!(matches!(
+            res,
+            Err(ContractError::Escape(EscapeError::HostStillActive {}))
+        ));
+        env.block.time = env.block.time.plus_seconds(1);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("stranger", &[]),
+            ExecuteMsg::Escape {},
+        );
+        assert!(matches!(
+            res,
+            Err(ContractError::Escape(EscapeError::Unauthorized {}))
+        ));
+        let res = execute(deps.as_mut(), env, backup, ExecuteMsg::Escape {})
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "backup".to_string(),
+                amount: coins(10, "token"),
+            })
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use cosmwasm_std::StdError;
+use slave_escape::EscapeError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,4 +12,19 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("Unknown reply ID: {0}")]
+    UnknownReplyID(u64),
+
+    #[error("{0}")]
+    Escape(#[from] EscapeError),
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from newer version {previous_version}")]
+    CannotDowngrade { previous_version: String },
+
+    #[error("Invalid contract version: {0}")]
+    InvalidVersion(String),
 }
@@ -1,10 +1,58 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, CosmosMsg};
+pub use slave_escape::msg::{EscapeGuardians, EscapeHatch};
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub owner: Addr,
     pub chain: String,
+    pub escape_hatch: Option<EscapeHatch>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    ExecuteBatch {
+        msgs: Vec<CosmosMsg>,
+        mode: BatchMode,
+    },
+    /// Sent by the host within a batch, `None` disables the escape hatch.
+    /// A new hatch only takes effect after
+    /// [`slave_escape::ESCAPE_HATCH_DELAY`].
+    SetEscapeHatch {
+        hatch: Option<EscapeHatch>,
+    },
+    /// Sent by the backup to withdraw the balance of an abandoned slave.
+    Escape {},
+    /// Sent by an escape guardian, the balance goes to `recipient` once
+    /// enough guardians agree.
+    ApproveEscape {
+        recipient: String,
+    },
+}
+
+#[cw_serde]
+pub enum BatchMode {
+    /// Any failure reverts the whole batch.
+    Atomic,
+    /// Failed messages are reverted and reported, the rest still runs.
+    BestEffort,
+}
+
+/// Set as response data of `ExecuteBatch`.
+#[cw_serde]
+#[derive(Default)]
+pub struct BatchResult {
+    pub total: u32,
+    pub failed: Vec<BatchFailure>,
+}
+
+#[cw_serde]
+pub struct BatchFailure {
+    pub index: u32,
+    pub error: String,
 }
 
 #[cw_serde]
@@ -3,9 +3,13 @@ use cw_storage_plus::Item;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::msg::BatchResult;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct State {
     pub owner: Addr,
 }
 
 pub const STATE: Item<State> = Item::new("state");
+/// Outcome of the best-effort batch currently being executed.
+pub const BATCH_RESULT: Item<BatchResult> = Item::new("batch_result");
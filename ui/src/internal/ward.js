@@ -177,8 +177,18 @@ export default class Ward {
     const host = await this.getHostContract();
     if (!this._slaveContracts) {
       const client = await this.getClient(HOST_CHAIN.chainId);
-      const response = await client.queryContractSmart(host, {get_slaves: {}});
-      this._slaveContracts = response.slaves;
+      const slaves = {};
+      let startAfter;
+      // The host returns a page at a time, ordered by chain
+      for (;;) {
+        const {slaves: page} = await client.queryContractSmart(host, {
+          get_slaves: {start_after: startAfter, limit: 30},
+        });
+        if (page.length === 0) break;
+        for (const {chain, addr} of page) slaves[chain] = addr;
+        startAfter = page[page.length - 1].chain;
+      }
+      this._slaveContracts = slaves;
     }
     return this._slaveContracts;
   }
@@ -402,7 +402,7 @@ export class ManageOwnScreen extends MessageSender {
       new_owner,
     } = await this.ward.getRecoveryState();
     this.setState({
-      recoveryPool: members,
+      recoveryPool: members.map(({addr}) => addr),
       transferInProgress: recovery_method != null,
       newOwner: new_owner || '',
     });
@@ -414,7 +414,11 @@ export class ManageOwnScreen extends MessageSender {
     try {
       await this.sendMessage(
         {
-          add_recovery_member: {member: this.state.newRecoveryMember},
+          add_recovery_member: {
+            member: this.state.newRecoveryMember,
+            weight: 1,
+            required: false,
+          },
         },
         await this.ward.getHostContract(),
       );
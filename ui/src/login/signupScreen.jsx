@@ -180,7 +180,11 @@ export class NewAccountScreen extends React.Component {
       HOST_CHAIN.chainId,
       {
         create_host: {
-          recovery_pool: recoveryPool,
+          recovery_pool: recoveryPool.map((addr) => ({
+            addr,
+            weight: 1,
+            required: false,
+          })),
           approval_pool: [], // Unused for now
           recovery_approvals_needed: this.state.recoveryApprovalsNeeded,
           transfer_ownership_approvals_needed: